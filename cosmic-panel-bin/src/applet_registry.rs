@@ -0,0 +1,228 @@
+//! Index of the desktop entries of installed applets, kept up to date while the panel runs
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+use cosmic_panel_config::RestartPolicy;
+use freedesktop_desktop_entry::{default_paths, DesktopEntry, Iter};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use sctk::reexports::calloop::channel::SyncSender;
+use tracing::{error, info, warn};
+
+use crate::{space::AppletAutoClickAnchor, PanelCalloopMsg};
+
+/// the keys of a desktop entry which are used to launch an applet
+#[derive(Debug, Clone)]
+pub struct AppletDesktopEntry {
+    pub path: PathBuf,
+    pub exec: Option<String>,
+    pub requests_wayland_display: bool,
+    pub minimize_priority: Option<u32>,
+    pub auto_popup_hover_press: Option<AppletAutoClickAnchor>,
+    pub is_notification_applet: bool,
+    pub restart_policy: Option<RestartPolicy>,
+}
+
+impl AppletDesktopEntry {
+    fn read(path: &Path) -> Option<Self> {
+        let bytes = fs::read_to_string(path).ok()?;
+        let entry = DesktopEntry::decode(path, &bytes).ok()?;
+        Some(Self {
+            path: path.to_path_buf(),
+            exec: entry.exec().map(str::to_string),
+            requests_wayland_display: entry.desktop_entry("X-HostWaylandDisplay").is_some(),
+            minimize_priority: entry
+                .desktop_entry("X-MinimizeApplet")
+                .map(|p| p.parse::<u32>().unwrap_or_default()),
+            auto_popup_hover_press: entry
+                .desktop_entry("X-CosmicHoverPopup")
+                .map(|v| v.parse::<AppletAutoClickAnchor>().unwrap_or_default()),
+            is_notification_applet: entry.desktop_entry("X-NotificationsApplet").is_some(),
+            restart_policy: entry.desktop_entry("X-CosmicRestartPolicy").and_then(|p| {
+                p.parse::<RestartPolicy>()
+                    .map_err(|err| {
+                        warn!(
+                            "Invalid X-CosmicRestartPolicy in {}: {}",
+                            path.display(),
+                            err
+                        )
+                    })
+                    .ok()
+            }),
+        })
+    }
+}
+
+fn desktop_id(path: &Path) -> Option<&str> {
+    if path.extension().is_some_and(|e| e == "desktop") {
+        path.file_stem().and_then(|s| s.to_str())
+    } else {
+        None
+    }
+}
+
+/// position of the XDG application directory containing the path, lower positions take
+/// precedence
+fn precedence(dirs: &[PathBuf], path: &Path) -> usize {
+    dirs.iter()
+        .position(|dir| path.starts_with(dir))
+        .unwrap_or(dirs.len())
+}
+
+/// desktop entries of the XDG application directories, by desktop id
+#[derive(Debug, Default)]
+pub struct AppletRegistry {
+    /// entries with the precedence of their directory
+    entries: HashMap<String, (usize, AppletDesktopEntry)>,
+}
+
+impl AppletRegistry {
+    /// index the desktop entries of the XDG application directories
+    pub fn new() -> Self {
+        let dirs = default_paths();
+        let mut entries = HashMap::new();
+        for path in Iter::new(dirs.clone()) {
+            let Some(id) = desktop_id(&path) else {
+                continue;
+            };
+            // directories are searched in order of precedence, so the first match is used
+            if entries.contains_key(id) {
+                continue;
+            }
+            if let Some(entry) = AppletDesktopEntry::read(&path) {
+                entries.insert(id.to_string(), (precedence(&dirs, &path), entry));
+            }
+        }
+        info!("Indexed {} desktop entries", entries.len());
+        Self { entries }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&AppletDesktopEntry> {
+        self.entries.get(id).map(|(_, entry)| entry)
+    }
+
+    /// re-read the desktop entries of the changed paths
+    /// the directories are only searched again if the entry in use was removed
+    /// returns the ids of the entries which can now be launched, but could not be before
+    pub fn update(&mut self, paths: &[PathBuf]) -> Vec<String> {
+        let dirs = default_paths();
+        let mut paths = paths.to_vec();
+        paths.sort();
+        paths.dedup();
+
+        let mut added = Vec::new();
+        for path in paths {
+            let Some(id) = desktop_id(&path) else {
+                continue;
+            };
+            let was_launchable = self.get(id).is_some_and(|e| e.exec.is_some());
+            let path_precedence = precedence(&dirs, &path);
+            // precedence of the entry in use, and whether it is the changed one
+            let current = self
+                .entries
+                .get(id)
+                .map(|(p, entry)| (*p, entry.path == path));
+            match AppletDesktopEntry::read(&path) {
+                Some(entry) => {
+                    // the changed entry is shadowed by an entry in another directory
+                    if current.is_some_and(|(p, is_changed)| !is_changed && p < path_precedence) {
+                        continue;
+                    }
+                    self.entries
+                        .insert(id.to_string(), (path_precedence, entry));
+                }
+                None => {
+                    if !current.is_some_and(|(_, is_changed)| is_changed) {
+                        continue;
+                    }
+                    // the removed entry may have been shadowing one in another directory
+                    match Iter::new(dirs.clone())
+                        .filter(|p| desktop_id(p) == Some(id))
+                        .find_map(|p| {
+                            AppletDesktopEntry::read(&p).map(|e| (precedence(&dirs, &p), e))
+                        }) {
+                        Some(entry) => {
+                            self.entries.insert(id.to_string(), entry);
+                        }
+                        None => {
+                            self.entries.remove(id);
+                        }
+                    }
+                }
+            }
+            if !was_launchable && self.get(id).is_some_and(|e| e.exec.is_some()) {
+                added.push(id.to_string());
+            }
+        }
+        added
+    }
+}
+
+/// watch the XDG application directories, sending the paths of changed desktop entries
+/// directories which don't exist yet are waited for by watching their nearest existing parent
+pub fn watch_applets(panel_tx: SyncSender<PanelCalloopMsg>) -> anyhow::Result<()> {
+    let (created_tx, created_rx) = mpsc::channel();
+    let entries_tx = panel_tx.clone();
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event) if !event.kind.is_access() => {
+                let (paths, others): (Vec<_>, Vec<_>) = event
+                    .paths
+                    .into_iter()
+                    .partition(|p| desktop_id(p).is_some());
+                if !paths.is_empty() {
+                    let _ = entries_tx.send(PanelCalloopMsg::DesktopEntriesChanged(paths));
+                }
+                // the watcher can't be changed from its own event handler
+                if event.kind.is_create() && !others.is_empty() {
+                    let _ = created_tx.send(());
+                }
+            }
+            Ok(_) => {}
+            Err(err) => error!("Failed to watch desktop entries: {:?}", err),
+        })?;
+    let (_, mut missing) = watch_dirs(&mut watcher, default_paths());
+
+    std::thread::spawn(move || {
+        while created_rx.recv().is_ok() {
+            if missing.is_empty() {
+                continue;
+            }
+            let (created, still_missing) = watch_dirs(&mut watcher, missing);
+            missing = still_missing;
+            // entries may have been written before the directory was watched
+            let paths: Vec<_> = Iter::new(created).collect();
+            if !paths.is_empty() {
+                let _ = panel_tx.send(PanelCalloopMsg::DesktopEntriesChanged(paths));
+            }
+        }
+    });
+    Ok(())
+}
+
+/// watch the existing directories recursively, and the nearest existing parent of the others
+/// returns the directories which are watched, and the ones which don't exist yet
+fn watch_dirs(
+    watcher: &mut RecommendedWatcher,
+    dirs: Vec<PathBuf>,
+) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let (existing, missing): (Vec<_>, Vec<_>) = dirs.into_iter().partition(|dir| dir.is_dir());
+    for dir in &existing {
+        if let Err(err) = watcher.watch(dir, RecursiveMode::Recursive) {
+            error!("Failed to watch {}: {:?}", dir.display(), err);
+        }
+    }
+    for dir in &missing {
+        let Some(parent) = dir.ancestors().skip(1).find(|p| p.is_dir()) else {
+            continue;
+        };
+        if let Err(err) = watcher.watch(parent, RecursiveMode::NonRecursive) {
+            error!("Failed to watch {}: {:?}", parent.display(), err);
+        }
+    }
+    (existing, missing)
+}
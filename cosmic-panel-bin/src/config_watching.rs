@@ -2,10 +2,10 @@ use std::collections::HashMap;
 
 use crate::space_container::SpaceContainer;
 use anyhow::anyhow;
-use cosmic_config::{ConfigGet, CosmicConfigEntry};
-use cosmic_panel_config::{CosmicPanelConfig, CosmicPanelContainerConfig};
-use cosmic_theme::{palette, Theme, ThemeMode};
-use notify::RecommendedWatcher;
+use cosmic_config::CosmicConfigEntry;
+use cosmic_panel_config::{system_dir, CosmicPanelConfig, CosmicPanelContainerConfig, NAME};
+use cosmic_theme::{Theme, ThemeMode};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use smithay::reexports::calloop::{channel, LoopHandle};
 use tracing::{error, info};
 use xdg_shell_wrapper::shared_state::GlobalState;
@@ -20,10 +20,10 @@ enum ConfigUpdate {
 enum ThemeUpdate {
     /// is the theme light or dark
     Mode(bool),
-    /// dark theme bg change,
-    Dark(palette::Srgba),
-    /// light theme bg change,
-    Light(palette::Srgba),
+    /// dark theme change
+    Dark(Theme),
+    /// light theme change
+    Light(Theme),
 }
 
 pub fn watch_cosmic_theme(
@@ -36,18 +36,14 @@ pub fn watch_cosmic_theme(
 
     handle.insert_source(entries_rx, move |event, _, state| {
         match event {
-            channel::Event::Msg(ThemeUpdate::Dark(color)) => {
-                state
-                    .space
-                    .set_dark([color.red, color.green, color.blue, color.alpha]);
+            channel::Event::Msg(ThemeUpdate::Dark(theme)) => {
+                state.space.set_dark(theme);
             }
             channel::Event::Msg(ThemeUpdate::Mode(is_dark)) => {
                 state.space.set_theme_mode(is_dark);
             }
-            channel::Event::Msg(ThemeUpdate::Light(color)) => {
-                state
-                    .space
-                    .set_light([color.red, color.green, color.blue, color.alpha]);
+            channel::Event::Msg(ThemeUpdate::Light(theme)) => {
+                state.space.set_light(theme);
             }
             channel::Event::Closed => {}
         };
@@ -76,17 +72,13 @@ pub fn watch_cosmic_theme(
     let theme_watcher_light = config_light_helper
         .watch(move |helper, _keys| match Theme::get_entry(&helper) {
             Ok(entry) => {
-                entries_tx_clone
-                    .send(ThemeUpdate::Light(entry.bg_color()))
-                    .unwrap();
+                entries_tx_clone.send(ThemeUpdate::Light(entry)).unwrap();
             }
             Err((err, entry)) => {
                 for e in err {
                     error!("Failed to get theme entry value: {:?}", e);
                 }
-                entries_tx_clone
-                    .send(ThemeUpdate::Light(entry.bg_color()))
-                    .unwrap();
+                entries_tx_clone.send(ThemeUpdate::Light(entry)).unwrap();
             }
         })
         .map_err(|e| anyhow!(format!("{:?}", e)))?;
@@ -95,17 +87,13 @@ pub fn watch_cosmic_theme(
     let theme_watcher_dark = config_dark_helper
         .watch(move |helper, _keys| match Theme::get_entry(&helper) {
             Ok(entry) => {
-                entries_tx_clone
-                    .send(ThemeUpdate::Dark(entry.bg_color()))
-                    .unwrap();
+                entries_tx_clone.send(ThemeUpdate::Dark(entry)).unwrap();
             }
             Err((err, entry)) => {
                 for e in err {
                     error!("Failed to get theme entry value: {:?}", e);
                 }
-                entries_tx_clone
-                    .send(ThemeUpdate::Dark(entry.bg_color()))
-                    .unwrap();
+                entries_tx_clone.send(ThemeUpdate::Dark(entry)).unwrap();
             }
         })
         .map_err(|e| anyhow!(format!("{:?}", e)))?;
@@ -120,7 +108,7 @@ pub fn watch_cosmic_theme(
 pub fn watch_config(
     config: &CosmicPanelContainerConfig,
     handle: LoopHandle<GlobalState<SpaceContainer>>,
-) -> Result<HashMap<String, RecommendedWatcher>, Box<dyn std::error::Error>> {
+) -> Result<HashMap<String, Vec<RecommendedWatcher>>, Box<dyn std::error::Error>> {
     let (entries_tx, entries_rx) = channel::sync_channel::<ConfigUpdate>(30);
 
     let entries_tx_clone = entries_tx.clone();
@@ -135,45 +123,9 @@ pub fn watch_config(
                     .collect::<Vec<String>>();
                 info!("Received entries: {:?}", to_update);
                 for entry in to_update {
-                    let cosmic_config = match CosmicPanelConfig::cosmic_config(&entry) {
-                        Ok(config) => config,
-                        Err(err) => {
-                            error!("Failed to load cosmic config: {:?}", err);
-                            return;
-                        }
-                    };
-
-                    let entry = match CosmicPanelConfig::get_entry(&cosmic_config) {
-                        Ok(entry) => entry,
-                        Err((err, entry)) => {
-                            for error in err {
-                                error!("Failed to get entry value: {:?}", error);
-                            }
-                            entry
-                        }
-                    };
-
-                    let entries_tx_clone = entries_tx_clone.clone();
-                    let name_clone = entry.name.clone();
-                    let helper = CosmicPanelConfig::cosmic_config(&name_clone)
-                        .expect("Failed to load cosmic config");
-                    let watcher = helper
-                        .watch(move |helper, _keys| {
-                            let new = match CosmicPanelConfig::get_entry(&helper) {
-                                Ok(entry) => entry,
-                                Err((err, entry)) => {
-                                    for error in err {
-                                        error!("Failed to get entry value: {:?}", error);
-                                    }
-                                    entry
-                                }
-                            };
-                            entries_tx_clone
-                                .send(ConfigUpdate::EntryChanged(new))
-                                .expect("Failed to send Config Update");
-                        })
-                        .expect("Failed to watch cosmic config");
-                    state.space.watchers.insert(entry.name.clone(), watcher);
+                    let entry = load_entry(&entry);
+                    let watchers = watch_entry(&entry.name, entries_tx_clone.clone());
+                    state.space.watchers.insert(entry.name.clone(), watchers);
 
                     state.space.update_space(
                         entry,
@@ -221,48 +173,108 @@ pub fn watch_config(
     );
 
     let entries_tx_clone = entries_tx.clone();
-    let entries_watcher = cosmic_config_entries
-        .watch(
-            move |helper, keys| match helper.get::<Vec<String>>(&keys[0]) {
-                Ok(entries) => {
-                    entries_tx_clone
-                        .send(ConfigUpdate::Entries(entries))
-                        .expect("Failed to send entries");
-                }
-                Err(err) => {
-                    error!("Failed to get entries: {:?}", err);
-                }
-            },
-        )
-        .expect("Failed to watch cosmic config");
+    let mut entries_watchers = vec![cosmic_config_entries
+        .watch(move |_helper, _keys| send_entries(&entries_tx_clone))
+        .expect("Failed to watch cosmic config")];
+    let entries_tx_clone = entries_tx.clone();
+    entries_watchers.extend(watch_system_layer(NAME, move || {
+        send_entries(&entries_tx_clone)
+    }));
 
-    let mut watchers = HashMap::from([("entries".to_string(), entries_watcher)]);
+    let mut watchers = HashMap::from([("entries".to_string(), entries_watchers)]);
 
     for entry in &config.config_list {
-        let entries_tx_clone = entries_tx.clone();
-        let name_clone = entry.name.clone();
-        let helper =
-            CosmicPanelConfig::cosmic_config(&name_clone).expect("Failed to load cosmic config");
-        info!("Watching panel config entry: {:?}", helper);
-        let watcher = helper
-            .watch(move |helper, keys| {
-                info!("Entry changed: {:?}", keys);
-                let new = match CosmicPanelConfig::get_entry(&helper) {
-                    Ok(entry) => entry,
-                    Err((err, entry)) => {
-                        for error in err {
-                            error!("Failed to get entry value: {:?}", error);
-                        }
-                        entry
-                    }
-                };
-                entries_tx_clone
-                    .send(ConfigUpdate::EntryChanged(new))
-                    .expect("Failed to send Config Update");
-            })
-            .expect("Failed to watch cosmic config");
-        watchers.insert(entry.name.clone(), watcher);
+        watchers.insert(
+            entry.name.clone(),
+            watch_entry(&entry.name, entries_tx.clone()),
+        );
     }
 
     Ok(watchers)
 }
+
+/// send the panel entries, which may be set in either config layer
+fn send_entries(entries_tx: &channel::SyncSender<ConfigUpdate>) {
+    match CosmicPanelContainerConfig::load_entry_names() {
+        Ok(entries) => {
+            entries_tx
+                .send(ConfigUpdate::Entries(entries))
+                .expect("Failed to send entries");
+        }
+        Err(err) => {
+            error!("Failed to get entries: {:?}", err);
+        }
+    }
+}
+
+/// load a panel entry from both config layers, logging errors
+fn load_entry(name: &str) -> CosmicPanelConfig {
+    match CosmicPanelConfig::load_layered(name) {
+        Ok(entry) => entry,
+        Err((err, entry)) => {
+            for error in err {
+                error!("Failed to get entry value: {:?}", error);
+            }
+            entry
+        }
+    }
+}
+
+/// watch both layers of a panel entry, sending the reloaded entry when either changes
+fn watch_entry(
+    name: &str,
+    entries_tx: channel::SyncSender<ConfigUpdate>,
+) -> Vec<RecommendedWatcher> {
+    let send_entry = {
+        let name = name.to_string();
+        move || {
+            entries_tx
+                .send(ConfigUpdate::EntryChanged(load_entry(&name)))
+                .expect("Failed to send Config Update");
+        }
+    };
+
+    let mut watchers = Vec::with_capacity(2);
+    match CosmicPanelConfig::cosmic_config(name) {
+        Ok(helper) => {
+            info!("Watching panel config entry: {:?}", helper);
+            let send_entry = send_entry.clone();
+            match helper.watch(move |_helper, keys| {
+                info!("Entry changed: {:?}", keys);
+                send_entry();
+            }) {
+                Ok(watcher) => watchers.push(watcher),
+                Err(err) => error!("Failed to watch panel config entry {}: {:?}", name, err),
+            }
+        }
+        Err(err) => error!("Failed to load cosmic config: {:?}", err),
+    }
+    watchers.extend(watch_system_layer(&format!("{NAME}.{name}"), send_entry));
+    watchers
+}
+
+/// watch the installed defaults of a config, which cosmic-config only watches in the user layer
+fn watch_system_layer(
+    name: &str,
+    on_change: impl Fn() + Send + 'static,
+) -> Option<RecommendedWatcher> {
+    let dir = system_dir(name)?;
+    let watcher =
+        notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event) if !event.kind.is_access() && !event.kind.is_other() => on_change(),
+            Ok(_) => {}
+            Err(err) => error!("Failed to watch system config: {:?}", err),
+        });
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(err) => {
+            error!("Failed to watch system config {}: {:?}", name, err);
+            return None;
+        }
+    };
+    if let Err(err) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+        error!("Failed to watch system config {}: {:?}", dir.display(), err);
+        return None;
+    }
+    Some(watcher)
+}
@@ -1,6 +1,8 @@
+mod applet_registry;
 mod config_watching;
 mod minimize;
 mod notifications;
+mod restart;
 mod space;
 mod space_container;
 
@@ -13,13 +15,17 @@ use config_watching::{watch_config, watch_cosmic_theme};
 use cosmic_panel_config::CosmicPanelConfig;
 use launch_pad::{ProcessKey, ProcessManager};
 use minimize::MinimizeApplet;
-use notifications::notifications_conn;
+use notifications::{notifications_conn, show_applet_error};
 use sctk::reexports::calloop::channel::SyncSender;
-use smithay::reexports::{calloop, wayland_server::backend::ClientId};
+use smithay::reexports::calloop::{
+    self,
+    timer::{TimeoutAction, Timer},
+};
 use std::{
     collections::HashMap,
     mem,
     os::fd::{AsRawFd, OwnedFd},
+    path::PathBuf,
     time::Duration,
 };
 use tokio::{runtime, sync::mpsc};
@@ -31,13 +37,18 @@ use xdg_shell_wrapper::{
 
 #[derive(Debug)]
 pub enum PanelCalloopMsg {
-    ClientSocketPair(ClientId),
     RestartSpace(CosmicPanelConfig, WlOutput),
     MinimizeRect {
         output: String,
         applet_info: MinimizeApplet,
     },
     UpdateToplevel(zcosmic_toplevel_handle_v1::ZcosmicToplevelHandleV1),
+    DesktopEntriesChanged(Vec<PathBuf>),
+    /// the process of an applet instance exited, `crashed` if it was not stopped by the panel
+    AppletExited {
+        instance: u64,
+        crashed: bool,
+    },
 }
 
 fn main() -> Result<()> {
@@ -61,7 +72,7 @@ fn main() -> Result<()> {
 
     let arg = std::env::args().nth(1);
     let usage = "USAGE: cosmic-panel";
-    let config = match arg.as_ref().map(|s| &s[..]) {
+    let mut config = match arg.as_ref().map(|s| &s[..]) {
         Some(arg) if arg == "--help" || arg == "-h" => {
             println!("{}", usage);
             std::process::exit(1);
@@ -72,7 +83,7 @@ fn main() -> Result<()> {
                 for e in errors {
                     error!("Panel Entry Error: {:?}", e);
                 }
-                let _ = c.write_entries();
+                // writing the entries back would override every key in the user layer
                 c
             }
         },
@@ -81,6 +92,13 @@ fn main() -> Result<()> {
             std::process::exit(1);
         }
     };
+    for issue in config.retain_valid() {
+        if issue.is_error() {
+            error!("Rejecting panel entry: {}", issue);
+        } else {
+            warn!("Panel entry issue: {}", issue);
+        }
+    }
 
     let (applet_tx, mut applet_rx) = mpsc::channel(200);
     let (calloop_tx, calloop_rx): (SyncSender<PanelCalloopMsg>, _) =
@@ -103,7 +121,11 @@ fn main() -> Result<()> {
         Ok(w) => mem::forget(w),
         Err(e) => error!("Error while watching cosmic theme: {:?}", e),
     };
+    if let Err(e) = applet_registry::watch_applets(calloop_tx.clone()) {
+        error!("Error while watching desktop entries: {:?}", e);
+    }
 
+    let loop_handle = event_loop.handle();
     event_loop
         .handle()
         .insert_source(
@@ -111,9 +133,6 @@ fn main() -> Result<()> {
             move |e, _, state: &mut GlobalState<space_container::SpaceContainer>| {
                 match e {
                     calloop::channel::Event::Msg(e) => match e {
-                        PanelCalloopMsg::ClientSocketPair(client_id) => {
-                            state.space.cleanup_client(client_id);
-                        }
                         PanelCalloopMsg::RestartSpace(config, o) => {
                             state.space.update_space(
                                 config,
@@ -132,6 +151,32 @@ fn main() -> Result<()> {
                             output,
                             applet_info,
                         } => minimize::set_rectangles(state, output, applet_info),
+                        PanelCalloopMsg::DesktopEntriesChanged(paths) => state
+                            .space
+                            .desktop_entries_changed(paths, &state.client_state.queue_handle),
+                        PanelCalloopMsg::AppletExited { instance, crashed } => {
+                            let Some(delay) = state.space.applet_exited(instance, crashed) else {
+                                return;
+                            };
+                            if let Err(err) =
+                                loop_handle.insert_source(
+                                    Timer::from_duration(delay),
+                                    move |_,
+                                          _,
+                                          state: &mut GlobalState<
+                                        space_container::SpaceContainer,
+                                    >| {
+                                        state.space.restart_applet(
+                                            instance,
+                                            &state.client_state.queue_handle,
+                                        );
+                                        TimeoutAction::Drop
+                                    },
+                                )
+                            {
+                                error!("Failed to schedule the restart of an applet: {}", err);
+                            }
+                        }
                     },
                     calloop::channel::Event::Closed => {}
                 };
@@ -143,16 +188,17 @@ fn main() -> Result<()> {
         let rt = runtime::Builder::new_current_thread()
             .enable_all()
             .build()?;
-        let mut process_ids: HashMap<String, Vec<ProcessKey>> = HashMap::new();
+        // processes of each space, with the instance ids of their applets
+        let mut process_ids: HashMap<String, Vec<(u64, ProcessKey)>> = HashMap::new();
 
         rt.block_on(async move {
             let process_manager = ProcessManager::new().await;
+            // crashed applets are stopped on exit, the panel restarts them following their
+            // restart policy, so launch_pad only reports whether an exit was requested
             let _ = process_manager
-                .set_restart_mode(launch_pad::RestartMode::ExponentialBackoff(
-                    Duration::from_millis(2),
-                ))
+                .set_restart_mode(launch_pad::RestartMode::Instant)
                 .await;
-            let _ = process_manager.set_max_restarts(999999).await;
+            let _ = process_manager.set_max_restarts(usize::MAX).await;
 
             let mut notifications_proxy =
                 match tokio::time::timeout(Duration::from_secs(1), notifications_conn()).await {
@@ -165,14 +211,15 @@ fn main() -> Result<()> {
 
             while let Some(msg) = applet_rx.recv().await {
                 match msg {
-                    space::AppletMsg::NewProcess(id, process) => {
+                    space::AppletMsg::NewProcess(id, instance, process) => {
                         if let Ok(key) = process_manager.start(process).await {
                             let entry = process_ids.entry(id).or_insert_with(|| Vec::new());
-                            entry.push(key);
+                            entry.push((instance, key));
                         }
                     }
                     space::AppletMsg::NewNotificationsProcess(
                         id,
+                        instance,
                         mut process,
                         mut env,
                         mut fds,
@@ -224,32 +271,44 @@ fn main() -> Result<()> {
                         info!("Starting notifications applet");
                         if let Ok(key) = process_manager.start(process).await {
                             let entry = process_ids.entry(id).or_insert_with(|| Vec::new());
-                            entry.push(key);
+                            entry.push((instance, key));
                         }
                     }
-                    space::AppletMsg::ClientSocketPair(client_id) => {
-                        let _ = calloop_tx.send(PanelCalloopMsg::ClientSocketPair(client_id));
+                    space::AppletMsg::ShowError { applet, error } => {
+                        // the notifications daemon may need to be activated first, which must not
+                        // delay the handling of the applet processes
+                        tokio::spawn(async move {
+                            match tokio::time::timeout(
+                                Duration::from_secs(5),
+                                show_applet_error(&applet, &error),
+                            )
+                            .await
+                            {
+                                Ok(Ok(())) => {}
+                                Ok(Err(err)) => {
+                                    error!("Failed to show the error of {}: {}", applet, err)
+                                }
+                                Err(_) => error!("Timed out showing the error of {}", applet),
+                            }
+                        });
                     }
                     space::AppletMsg::Cleanup(id) => {
-                        for id in process_ids.remove(&id).unwrap_or_default() {
-                            let _ = process_manager.stop_process(id).await;
+                        for (_, key) in process_ids.remove(&id).unwrap_or_default() {
+                            let _ = process_manager.stop_process(key).await;
                         }
                     }
-                    space::AppletMsg::NeedNewNotificationFd(sender) => {
-                        let Some(proxy) = notifications_proxy.as_mut() else {
-                            warn!("Can't start notifications applet without a connection");
+                    space::AppletMsg::HandOver { from, to } => {
+                        let keys = process_ids.remove(&from).unwrap_or_default();
+                        process_ids.entry(to).or_default().extend(keys);
+                    }
+                    space::AppletMsg::StopApplet(id, instance) => {
+                        let Some(keys) = process_ids.get_mut(&id) else {
                             continue;
                         };
-                        let fd = match proxy.get_fd().await {
-                            Ok(fd) => fd,
-                            Err(err) => {
-                                error!("Failed to get fd for the notifications applet {}", err);
-                                continue;
-                            }
-                        };
-                        let fd = OwnedFd::from(fd);
-
-                        _ = sender.send(fd);
+                        if let Some(i) = keys.iter().position(|(i, _)| *i == instance) {
+                            let (_, key) = keys.swap_remove(i);
+                            let _ = process_manager.stop_process(key).await;
+                        }
                     }
                 };
             }
@@ -4,12 +4,15 @@ use smithay::reexports::rustix::{
     self,
     io::{fcntl_getfd, fcntl_setfd, FdFlags},
 };
-use std::os::{
-    fd::{FromRawFd, OwnedFd, RawFd},
-    unix::net::UnixStream,
+use std::{
+    collections::HashMap,
+    os::{
+        fd::{FromRawFd, OwnedFd, RawFd},
+        unix::net::UnixStream,
+    },
 };
 use tracing::info;
-use zbus::{connection::Builder, proxy};
+use zbus::{connection::Builder, proxy, zvariant::Value, Connection};
 
 #[proxy(
     default_service = "com.system76.NotificationsSocket",
@@ -20,6 +23,45 @@ trait NotificationsSocket {
     /// get an fd for an applet
     fn get_fd(&self) -> zbus::Result<zbus::zvariant::OwnedFd>;
 }
+#[proxy(
+    default_service = "org.freedesktop.Notifications",
+    interface = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: &HashMap<&str, &Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+}
+
+/// show the error of a failed applet as a desktop notification
+pub async fn show_applet_error(applet: &str, error: &str) -> Result<()> {
+    let conn = Connection::session().await?;
+    let proxy = NotificationsProxy::new(&conn).await?;
+    proxy
+        .notify(
+            "cosmic-panel",
+            0,
+            "dialog-warning-symbolic",
+            &format!("{applet} failed"),
+            error,
+            &[],
+            &HashMap::new(),
+            -1,
+        )
+        .await?;
+    Ok(())
+}
+
 pub async fn notifications_conn() -> Result<NotificationsSocketProxy<'static>> {
     info!("Connecting to notifications daemon");
     let fd_num = std::env::var(PANEL_NOTIFICATIONS_FD)?;
@@ -0,0 +1,135 @@
+//! Crash-loop detection for applets, following their restart policy
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use cosmic_panel_config::{GiveUpAction, RestartPolicy};
+
+/// what to do with an applet which exited with an error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartDecision {
+    /// restart the applet after the delay
+    Restart(Duration),
+    /// the applet is crash-looping, it is restarted once more after the delay if there is one
+    CrashLoop(Option<Duration>),
+}
+
+/// recent restarts of an applet
+#[derive(Debug, Default)]
+pub struct RestartTracker {
+    restarts: VecDeque<Instant>,
+}
+
+impl RestartTracker {
+    /// record that the applet exited with an error, and decide whether it is restarted
+    pub fn exited(&mut self, policy: &RestartPolicy, now: Instant) -> RestartDecision {
+        let window = policy.window();
+        while self
+            .restarts
+            .front()
+            .is_some_and(|t| now.duration_since(*t) > window)
+        {
+            self.restarts.pop_front();
+        }
+
+        if self.restarts.len() >= policy.max_restarts as usize {
+            // a retry starts with a fresh window
+            self.restarts.clear();
+            return RestartDecision::CrashLoop(match policy.give_up {
+                GiveUpAction::Stop => None,
+                GiveUpAction::RetryAfter(secs) => Some(Duration::from_secs(secs.into())),
+            });
+        }
+
+        let delay = policy.delay(self.restarts.len() as u32);
+        self.restarts.push_back(now);
+        RestartDecision::Restart(delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn restart_policy(give_up: GiveUpAction) -> RestartPolicy {
+        RestartPolicy {
+            max_restarts: 3,
+            window: 10,
+            backoff: 100,
+            max_backoff: 250,
+            give_up,
+        }
+    }
+
+    fn restart(millis: u64) -> RestartDecision {
+        RestartDecision::Restart(Duration::from_millis(millis))
+    }
+
+    #[test]
+    fn backoff() {
+        let policy = restart_policy(GiveUpAction::Stop);
+        let mut tracker = RestartTracker::default();
+        let now = Instant::now();
+        assert_eq!(tracker.exited(&policy, now), restart(100));
+        assert_eq!(tracker.exited(&policy, now), restart(200));
+        // capped by max_backoff
+        assert_eq!(tracker.exited(&policy, now), restart(250));
+    }
+
+    #[test]
+    fn window() {
+        let policy = restart_policy(GiveUpAction::Stop);
+        let mut tracker = RestartTracker::default();
+        let now = Instant::now();
+        assert_eq!(tracker.exited(&policy, now), restart(100));
+        assert_eq!(
+            tracker.exited(&policy, now + Duration::from_secs(5)),
+            restart(200)
+        );
+        // the first restart left the window
+        assert_eq!(
+            tracker.exited(&policy, now + Duration::from_secs(11)),
+            restart(200)
+        );
+        assert_eq!(
+            tracker.exited(&policy, now + Duration::from_secs(12)),
+            restart(250)
+        );
+        // every restart left the window
+        assert_eq!(
+            tracker.exited(&policy, now + Duration::from_secs(30)),
+            restart(100)
+        );
+    }
+
+    #[test]
+    fn crash_loop() {
+        let now = Instant::now();
+        let mut tracker = RestartTracker::default();
+        let policy = restart_policy(GiveUpAction::Stop);
+        for _ in 0..3 {
+            assert!(matches!(
+                tracker.exited(&policy, now),
+                RestartDecision::Restart(_)
+            ));
+        }
+        assert_eq!(
+            tracker.exited(&policy, now),
+            RestartDecision::CrashLoop(None)
+        );
+
+        let mut tracker = RestartTracker::default();
+        let policy = restart_policy(GiveUpAction::RetryAfter(30));
+        for _ in 0..3 {
+            tracker.exited(&policy, now);
+        }
+        assert_eq!(
+            tracker.exited(&policy, now),
+            RestartDecision::CrashLoop(Some(Duration::from_secs(30)))
+        );
+        // a retry starts with a fresh window
+        assert_eq!(tracker.exited(&policy, now), restart(100));
+    }
+}
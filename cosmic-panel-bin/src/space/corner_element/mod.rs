@@ -1,11 +1,12 @@
 use std::cell::RefCell;
 
+use cosmic_panel_config::{GradientStop, MAX_GRADIENT_STOPS};
 use smithay::{
     backend::renderer::{
         element::{Element, Kind, RenderElement, UnderlyingStorage},
         gles::{
             element::PixelShaderElement,
-            ffi::{BLEND, FUNC_ADD, SRC_ALPHA, ZERO},
+            ffi::{BLEND, FUNC_ADD, ONE, ONE_MINUS_SRC_ALPHA, SRC_ALPHA, ZERO},
             GlesError, GlesFrame, GlesPixelProgram, GlesRenderer, Uniform, UniformName,
             UniformType,
         },
@@ -25,9 +26,73 @@ pub struct RoundedRectangleSettings {
     pub rad_br: f32,
     pub loc: [f32; 2],
     pub rect_size: [f32; 2],
+    /// fill the rectangle with a gradient, instead of masking the panel
+    pub gradient: Option<GradientSettings>,
+    pub border: Option<BorderSettings>,
+    pub shadow: Option<ShadowSettings>,
 }
 
-pub struct RoundedRectangleShaderElement(PixelShaderElement);
+/// an outline inside the edge of the rectangle
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct BorderSettings {
+    pub width: f32,
+    /// straight RGBA color
+    pub color: [f32; 4],
+}
+
+/// a blurred copy of the rectangle, visible outside of it
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSettings {
+    pub blur: f32,
+    pub offset: [f32; 2],
+    /// straight RGBA color
+    pub color: [f32; 4],
+}
+
+/// a linear gradient with exactly [`MAX_GRADIENT_STOPS`] stops
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct GradientSettings {
+    /// angle in radians, clockwise from pointing up
+    pub angle: f32,
+    /// offsets of the stops, in ascending order
+    pub offsets: [f32; MAX_GRADIENT_STOPS],
+    /// straight RGBA colors of the stops
+    pub colors: [[f32; 4]; MAX_GRADIENT_STOPS],
+}
+
+impl GradientSettings {
+    pub fn new(stops: &[GradientStop], angle: f32, opacity: f32) -> Self {
+        let mut stops: Vec<_> = stops
+            .iter()
+            .map(|s| {
+                let mut color = s.color.to_rgba();
+                color[3] *= opacity;
+                (s.offset.clamp(0.0, 1.0), color)
+            })
+            .collect();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        stops.truncate(MAX_GRADIENT_STOPS);
+
+        let mut settings = Self {
+            angle: angle.to_radians(),
+            ..Default::default()
+        };
+        // pad with the last stop, so the unused stops have no effect
+        let last = stops.last().copied().unwrap_or((1.0, [0.0; 4]));
+        for i in 0..MAX_GRADIENT_STOPS {
+            let (offset, color) = stops.get(i).copied().unwrap_or(last);
+            settings.offsets[i] = offset;
+            settings.colors[i] = color;
+        }
+        settings
+    }
+}
+
+pub struct RoundedRectangleShaderElement {
+    element: PixelShaderElement,
+    /// whether the element masks everything below it, rather than being drawn over it
+    mask: bool,
+}
 
 impl RoundedRectangleShader {
     pub fn get(renderer: &GlesRenderer) -> GlesPixelProgram {
@@ -40,25 +105,49 @@ impl RoundedRectangleShader {
             .clone()
     }
 
+    /// the fill of the rectangle, or its mask
     pub fn element(
         renderer: &GlesRenderer,
         geo: Rectangle<i32, Logical>,
         settings: RoundedRectangleSettings,
+    ) -> RoundedRectangleShaderElement {
+        Self::cached_element(renderer, geo, settings, false)
+    }
+
+    /// the border and shadow of the rectangle, drawn over everything else
+    pub fn decoration_element(
+        renderer: &GlesRenderer,
+        geo: Rectangle<i32, Logical>,
+        settings: RoundedRectangleSettings,
+    ) -> RoundedRectangleShaderElement {
+        Self::cached_element(renderer, geo, settings, true)
+    }
+
+    fn cached_element(
+        renderer: &GlesRenderer,
+        geo: Rectangle<i32, Logical>,
+        settings: RoundedRectangleSettings,
+        decoration: bool,
     ) -> RoundedRectangleShaderElement {
         let user_data = renderer.egl_context().user_data();
         user_data.insert_if_missing(|| {
-            RefCell::new(None::<(RoundedRectangleSettings, PixelShaderElement)>)
+            RefCell::new([None::<(RoundedRectangleSettings, PixelShaderElement)>, None])
         });
+        // the fill and the decoration are cached separately, as they are drawn in the same frame
         let mut cache = user_data
-            .get::<RefCell<Option<(RoundedRectangleSettings, PixelShaderElement)>>>()
+            .get::<RefCell<[Option<(RoundedRectangleSettings, PixelShaderElement)>; 2]>>()
             .unwrap()
             .borrow_mut();
+        let cache = &mut cache[decoration as usize];
 
         let elem = cache
             .take()
             .filter(|(s, _)| *s == settings)
             .unwrap_or_else(|| {
                 let shader = Self::get(renderer);
+                let gradient = settings.gradient.unwrap_or_default();
+                let border = settings.border.unwrap_or_default();
+                let shadow = settings.shadow.unwrap_or_default();
                 (
                     settings,
                     PixelShaderElement::new(
@@ -73,6 +162,26 @@ impl RoundedRectangleShader {
                             Uniform::new("rad_br", settings.rad_br),
                             Uniform::new("loc", settings.loc),
                             Uniform::new("rect_size", settings.rect_size),
+                            Uniform::new(
+                                "gradient",
+                                if settings.gradient.is_some() {
+                                    1.0
+                                } else {
+                                    0.0
+                                },
+                            ),
+                            Uniform::new("angle", gradient.angle),
+                            Uniform::new("stop_offsets", gradient.offsets),
+                            Uniform::new("stop_color_0", gradient.colors[0]),
+                            Uniform::new("stop_color_1", gradient.colors[1]),
+                            Uniform::new("stop_color_2", gradient.colors[2]),
+                            Uniform::new("stop_color_3", gradient.colors[3]),
+                            Uniform::new("decoration", if decoration { 1.0 } else { 0.0 }),
+                            Uniform::new("border_width", border.width),
+                            Uniform::new("border_color", border.color),
+                            Uniform::new("shadow_blur", shadow.blur),
+                            Uniform::new("shadow_offset", shadow.offset),
+                            Uniform::new("shadow_color", shadow.color),
                         ],
                         Kind::Unspecified,
                     ),
@@ -84,7 +193,10 @@ impl RoundedRectangleShader {
         if elem.geometry(1.0.into()).to_logical(1) != geo {
             elem.resize(geo, None);
         }
-        RoundedRectangleShaderElement(elem.clone())
+        RoundedRectangleShaderElement {
+            element: elem.clone(),
+            mask: !decoration && settings.gradient.is_none(),
+        }
     }
 }
 
@@ -109,6 +221,19 @@ pub fn init_shaders(gles_renderer: &mut GlesRenderer) -> Result<(), GlesError> {
             UniformName::new("rad_br", UniformType::_1f),
             UniformName::new("loc", UniformType::_2f),
             UniformName::new("rect_size", UniformType::_2f),
+            UniformName::new("gradient", UniformType::_1f),
+            UniformName::new("angle", UniformType::_1f),
+            UniformName::new("stop_offsets", UniformType::_4f),
+            UniformName::new("stop_color_0", UniformType::_4f),
+            UniformName::new("stop_color_1", UniformType::_4f),
+            UniformName::new("stop_color_2", UniformType::_4f),
+            UniformName::new("stop_color_3", UniformType::_4f),
+            UniformName::new("decoration", UniformType::_1f),
+            UniformName::new("border_width", UniformType::_1f),
+            UniformName::new("border_color", UniformType::_4f),
+            UniformName::new("shadow_blur", UniformType::_1f),
+            UniformName::new("shadow_offset", UniformType::_2f),
+            UniformName::new("shadow_color", UniformType::_4f),
         ],
     )?;
 
@@ -122,19 +247,19 @@ pub fn init_shaders(gles_renderer: &mut GlesRenderer) -> Result<(), GlesError> {
 
 impl Element for RoundedRectangleShaderElement {
     fn id(&self) -> &smithay::backend::renderer::element::Id {
-        self.0.id()
+        self.element.id()
     }
 
     fn current_commit(&self) -> smithay::backend::renderer::utils::CommitCounter {
-        self.0.current_commit()
+        self.element.current_commit()
     }
 
     fn src(&self) -> Rectangle<f64, Buffer> {
-        self.0.src()
+        self.element.src()
     }
 
     fn geometry(&self, scale: smithay::utils::Scale<f64>) -> Rectangle<i32, Physical> {
-        self.0.geometry(scale)
+        self.element.geometry(scale)
     }
 }
 
@@ -146,12 +271,23 @@ impl RenderElement<GlesRenderer> for RoundedRectangleShaderElement {
         dst: Rectangle<i32, Physical>,
         damage: &[Rectangle<i32, Physical>],
     ) -> Result<(), GlesError> {
+        if !self.mask {
+            // gradients and decorations are blended like any other element
+            _ = frame.with_context(|gl| unsafe {
+                gl.Enable(BLEND);
+                gl.BlendFunc(ONE, ONE_MINUS_SRC_ALPHA);
+                gl.BlendEquation(FUNC_ADD);
+            });
+            return self.element.draw(frame, src, dst, damage);
+        }
+
+        // mask everything drawn before with the alpha of the rounded rectangle
         _ = frame.with_context(|gl| unsafe {
             gl.Enable(BLEND);
             gl.BlendFuncSeparate(ZERO, SRC_ALPHA, ZERO, SRC_ALPHA);
             gl.BlendEquation(FUNC_ADD);
         });
-        let res = self.0.draw(frame, src, dst, damage);
+        let res = self.element.draw(frame, src, dst, damage);
         _ = frame.with_context(|gl| unsafe {
             gl.Disable(BLEND);
         });
@@ -159,6 +295,6 @@ impl RenderElement<GlesRenderer> for RoundedRectangleShaderElement {
     }
 
     fn underlying_storage(&self, renderer: &mut GlesRenderer) -> Option<UnderlyingStorage> {
-        self.0.underlying_storage(renderer)
+        self.element.underlying_storage(renderer)
     }
 }
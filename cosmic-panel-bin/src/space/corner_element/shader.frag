@@ -15,6 +15,24 @@ uniform float rad_br;
 uniform vec2 loc;
 uniform vec2 rect_size;
 
+// 1. to fill the rectangle with the gradient, 0. to only output its alpha
+uniform float gradient;
+// angle of the gradient in radians, clockwise from pointing up
+uniform float angle;
+uniform vec4 stop_offsets;
+uniform vec4 stop_color_0;
+uniform vec4 stop_color_1;
+uniform vec4 stop_color_2;
+uniform vec4 stop_color_3;
+
+// 1. to draw the border and shadow of the rectangle instead of its fill
+uniform float decoration;
+uniform float border_width;
+uniform vec4 border_color;
+uniform float shadow_blur;
+uniform vec2 shadow_offset;
+uniform vec4 shadow_color;
+
 float sdRoundBox(in vec2 p,in vec2 b,in vec4 r)
 {
     r.xy=(p.x>0.)?r.xy:r.zw;
@@ -23,6 +41,20 @@ float sdRoundBox(in vec2 p,in vec2 b,in vec4 r)
     return min(max(q.x,q.y),0.)+length(max(q,0.))-r.x;
 }
 
+vec4 gradientColor(in vec2 pos)
+{
+    vec2 dir=vec2(sin(angle),cos(angle));
+    float len=abs(rect_size.x*dir.x)+abs(rect_size.y*dir.y);
+    float t=dot(pos-(loc+rect_size/2.),dir)/max(len,1.)+.5;
+    
+    // unused stops repeat the last one, so mixing them has no effect
+    vec4 color=stop_color_0;
+    color=mix(color,stop_color_1,clamp((t-stop_offsets.x)/max(stop_offsets.y-stop_offsets.x,.0001),0.,1.));
+    color=mix(color,stop_color_2,clamp((t-stop_offsets.y)/max(stop_offsets.z-stop_offsets.y,.0001),0.,1.));
+    color=mix(color,stop_color_3,clamp((t-stop_offsets.z)/max(stop_offsets.w-stop_offsets.z,.0001),0.,1.));
+    return color;
+}
+
 void main()
 {
     vec2 p=2.*gl_FragCoord.xy-(rect_size+loc*2.);
@@ -68,6 +100,23 @@ void main()
     
     float a=1.-smoothstep(1.-5.*delta/6.,1.+delta/6.,1.+d);
     
-    gl_FragColor=vec4(0.,0.,0.,a);
+    if(decoration>.5){
+        // distances of the sdf are doubled, like the coordinates
+        float border=border_width>0.?a*(1.-smoothstep(border_width-.5,border_width+.5,-d/2.)):0.;
+        
+        // the shadow is a blurred copy of the rectangle, which is only visible outside of it
+        vec2 sp=2.*(pos-shadow_offset)-(rect_size+loc*2.);
+        float sd=sdRoundBox(sp,si,ra)/2.;
+        float shadow=(1.-smoothstep(0.,max(shadow_blur,1.),sd))*(1.-a);
+        
+        vec4 b=border_color*border;
+        vec4 s=shadow_color*shadow;
+        gl_FragColor=vec4(b.rgb*border_color.a,b.a)+vec4(s.rgb*shadow_color.a,s.a)*(1.-b.a);
+    }else if(gradient>.5){
+        vec4 color=gradientColor(pos);
+        gl_FragColor=vec4(color.rgb*color.a,color.a)*a;
+    }else{
+        gl_FragColor=vec4(0.,0.,0.,a);
+    }
 }
 
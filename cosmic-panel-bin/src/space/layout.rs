@@ -2,17 +2,22 @@ use std::slice::IterMut;
 
 use crate::{
     minimize::MinimizeApplet,
-    space::{corner_element::RoundedRectangleSettings, Alignment},
+    space::corner_element::{
+        BorderSettings, GradientSettings, RoundedRectangleSettings, ShadowSettings,
+    },
 };
 
-use super::PanelSpace;
-use cosmic_panel_config::PanelAnchor;
-use itertools::{chain, Itertools};
+use super::{
+    panel_space::{AppletPlaceholder, AppletStatus},
+    PanelSpace,
+};
+use cosmic_panel_config::{CosmicPanelBackground, PanelAnchor, PanelKind, ZoneAlignment};
+use itertools::Itertools;
 use sctk::shell::WaylandSurface;
 use smithay::{
     desktop::Window,
     reexports::wayland_server::Resource,
-    utils::{IsAlive, Physical, Rectangle, Size},
+    utils::{IsAlive, Logical, Physical, Rectangle, Size},
 };
 
 impl PanelSpace {
@@ -28,37 +33,13 @@ impl PanelSpace {
         let anchor = self.config.anchor();
         let spacing_u32 = self.config.spacing() as u32;
         let spacing_scaled = spacing_u32 as f64 * self.scale;
-        // First try partitioning the panel evenly into N spaces.
-        // If all windows fit into each space, then set their offsets and return.
         let list_thickness = match anchor {
             PanelAnchor::Left | PanelAnchor::Right => self.dimensions.w,
             PanelAnchor::Top | PanelAnchor::Bottom => self.dimensions.h,
         };
-        let is_dock = !self.config.expand_to_edges();
-
-        let mut num_lists: u32 = 0;
-        if self
-            .config
-            .plugins_wings
-            .as_ref()
-            .map(|l| l.0.len() + l.1.len())
-            .unwrap_or_default()
-            > 0
-        {
-            num_lists += 2;
-        }
-        if self
-            .config
-            .plugins_center
-            .as_ref()
-            .map(|l| l.len())
-            .unwrap_or_default()
-            > 0
-        {
-            num_lists += 1;
-        }
+        let is_dock = self.config.is_dock();
 
-        let make_indices_contiguous = |windows: &mut Vec<(usize, Window, Option<u32>)>| {
+        let make_indices_contiguous = |windows: &mut Vec<(usize, Slot, Option<u32>)>| {
             windows.sort_by(|(a_i, _, _), (b_i, _, _)| a_i.cmp(b_i));
             for (j, (i, _, _)) in windows.iter_mut().enumerate() {
                 *i = j;
@@ -93,7 +74,9 @@ impl PanelSpace {
                 unmap
             })
             .collect_vec();
-        for w in self.unmapped.drain(..).collect_vec() {
+        // windows hidden for the length of the bar are laid out again
+        let was_truncated = std::mem::take(&mut self.truncated);
+        for w in self.unmapped.drain(..).chain(was_truncated).collect_vec() {
             if w.alive() && {
                 let size = w.bbox().size.to_f64().downscale(self.scale).to_i32_round();
 
@@ -122,124 +105,121 @@ impl PanelSpace {
 
         self.space.refresh();
 
-        let mut windows_right = to_map
-            .iter()
-            .cloned()
-            .filter_map(|w| {
-                self.clients_right
-                    .lock()
-                    .unwrap()
-                    .iter()
-                    .enumerate()
-                    .find_map(|(i, c)| {
-                        if Some(c.client.id())
-                            == w.toplevel()
-                                .expect("Missing toplevel")
-                                .wl_surface()
-                                .client()
-                                .map(|c| c.id())
-                        {
-                            Some((i, w.clone(), c.minimize_priority))
-                        } else {
-                            None
-                        }
-                    })
-            })
-            .collect_vec();
-        make_indices_contiguous(&mut windows_right);
-
-        let mut windows_center = to_map
+        let window_client = |w: &Window| {
+            w.toplevel()
+                .expect("Missing toplevel")
+                .wl_surface()
+                .client()
+                .map(|c| c.id())
+        };
+        // applets whose window is too large don't keep their place
+        let unmapped_clients = self
+            .unmapped
             .iter()
-            .cloned()
-            .filter_map(|w| {
-                self.clients_center
-                    .lock()
-                    .unwrap()
-                    .iter()
-                    .enumerate()
-                    .find_map(|(i, c)| {
-                        if Some(c.client.id())
-                            == w.toplevel()
-                                .expect("Missing toplevel")
-                                .wl_surface()
-                                .client()
-                                .map(|c| c.id())
-                        {
-                            Some((i, w.clone(), c.minimize_priority))
-                        } else {
-                            None
-                        }
-                    })
-            })
+            .filter(|w| w.alive())
+            .filter_map(window_client)
             .collect_vec();
-        make_indices_contiguous(&mut windows_center);
-        let mut windows_left = to_map
+        let placeholder_side = ((self.config.get_applet_icon_size(true)
+            + 2 * self.config.get_applet_padding(true) as u32)
+            as f64
+            * self.scale)
+            .round() as i32;
+
+        // windows and placeholders of each non-empty zone, in the order of the zone's applets
+        let mut zones = self
+            .config
+            .zones
             .iter()
-            .cloned()
-            .filter_map(|w| {
-                self.clients_left
-                    .lock()
-                    .unwrap()
-                    .iter()
-                    .enumerate()
-                    .find_map(|(i, c)| {
-                        if Some(c.client.id())
-                            == w.toplevel()
-                                .expect("Missing toplevel")
-                                .wl_surface()
-                                .client()
-                                .map(|c| c.id())
-                        {
-                            Some((i, w.clone(), c.minimize_priority))
-                        } else {
+            .zip(self.clients.iter())
+            .filter_map(|(zone, clients)| {
+                let mut clients = clients.lock().unwrap();
+                let mut windows = Vec::new();
+                for (i, c) in clients.iter_mut().enumerate() {
+                    let client_id = Some(c.client.id());
+                    let len = windows.len();
+                    for w in to_map.iter().filter(|w| window_client(w) == client_id) {
+                        c.status = AppletStatus::Running;
+                        c.last_size = Some(w.bbox().size);
+                        windows.push((i, Slot::Window(w.clone()), c.minimize_priority));
+                    }
+                    if windows.len() > len || unmapped_clients.contains(&c.client.id()) {
+                        continue;
+                    }
+                    let slot = match &c.status {
+                        // a running applet may unmap its window on purpose, so only the place
+                        // of an applet which is known to be restarting is kept
+                        AppletStatus::Restarting => c.last_size.map(Slot::Reserved),
+                        AppletStatus::Failed(error) => Some(Slot::Placeholder {
+                            name: c.name.clone(),
+                            error: error.clone(),
+                            size: (placeholder_side, placeholder_side).into(),
+                        }),
+                        AppletStatus::Starting | AppletStatus::Running | AppletStatus::Stopped => {
                             None
                         }
-                    })
+                    };
+                    if let Some(slot) = slot {
+                        windows.push((i, slot, c.minimize_priority));
+                    }
+                }
+                if windows.is_empty() {
+                    return None;
+                }
+                make_indices_contiguous(&mut windows);
+                let spacing = zone.spacing.unwrap_or(spacing_u32) as f64;
+                let length_scaled = zone_length_scaled(&windows, spacing * self.scale, anchor);
+                Some(ZoneLayout {
+                    alignment: zone.alignment,
+                    spacing,
+                    length: length_scaled / self.scale,
+                    length_scaled,
+                    windows,
+                })
             })
             .collect_vec();
-        make_indices_contiguous(&mut windows_left);
-
-        fn map_fn(
-            (i, w, _): &(usize, Window, Option<u32>),
-            anchor: PanelAnchor,
-            alignment: Alignment,
-            _scale: f64,
-        ) -> (Alignment, usize, i32, i32) {
-            let bbox = w.bbox().size;
-
-            match anchor {
-                PanelAnchor::Left | PanelAnchor::Right => (alignment, *i, bbox.h, bbox.w),
-                PanelAnchor::Top | PanelAnchor::Bottom => (alignment, *i, bbox.w, bbox.h),
+
+        // applets at the end of the bar are hidden if they exceed the maximum length, starting
+        // with the last applet of the zone which is positioned last along the edge
+        let list_length_scaled = |zones: &[ZoneLayout]| {
+            zones.iter().map(|z| z.length_scaled).sum::<f64>()
+                + spacing_scaled * zones.len().saturating_sub(1) as f64
+        };
+        let max_bar_length = self.max_bar_length();
+        let max_list_length_scaled = max_bar_length as f64 * self.scale - padding_scaled * 2.0;
+        while max_bar_length > 0 && list_length_scaled(&zones) > max_list_length_scaled {
+            let Some(i) = (0..zones.len()).max_by(|a, b| {
+                edge_order(zones[*a].alignment)
+                    .total_cmp(&edge_order(zones[*b].alignment))
+                    .then(a.cmp(b))
+            }) else {
+                break;
+            };
+            let zone = &mut zones[i];
+            if let Some((_, Slot::Window(w), _)) = zone.windows.pop() {
+                self.truncated.push(w);
+            }
+            if zone.windows.is_empty() {
+                zones.remove(i);
+            } else {
+                zone.length_scaled =
+                    zone_length_scaled(&zone.windows, zone.spacing * self.scale, anchor);
+                zone.length = zone.length_scaled / self.scale;
+            }
+        }
+        // windows which stay hidden are not mapped, and the ones shown again are mapped below
+        for w in &self.truncated {
+            if self.space.elements().any(|e| e == w) {
+                tracing::debug!("{:?} exceeds the maximum panel length.", w.bbox().size);
+                self.space.unmap_elem(w);
             }
         }
 
-        let left = windows_left
-            .iter()
-            .map(|e| map_fn(e, anchor, Alignment::Left, self.scale));
-        let left_sum_scaled = left.clone().map(|(_, _, length, _)| length).sum::<i32>() as f64
-            + spacing_scaled as f64 * windows_left.len().saturating_sub(1) as f64;
-
-        let center = windows_center
-            .iter()
-            .map(|e| map_fn(e, anchor, Alignment::Center, self.scale));
-        let center_sum_scaled = center.clone().map(|(_, _, length, _)| length).sum::<i32>() as f64
-            + spacing_scaled * windows_center.len().saturating_sub(1) as f64;
-
-        let right = windows_right
-            .iter()
-            .map(|e| map_fn(e, anchor, Alignment::Right, self.scale));
-
-        let right_sum_scaled = right.clone().map(|(_, _, length, _)| length).sum::<i32>() as f64
-            + spacing_scaled * windows_right.len().saturating_sub(1) as f64;
-
-        let total_sum_scaled = left_sum_scaled + center_sum_scaled + right_sum_scaled;
-        let new_list_length = (total_sum_scaled as f64
-            + padding_scaled * 2.0
-            + spacing_scaled * num_lists.saturating_sub(1) as f64)
-            as i32;
+        let new_list_length = (list_length_scaled(&zones) + padding_scaled * 2.0) as i32;
         let new_list_thickness = (2.0 * padding_scaled
-            + chain!(left.clone(), center.clone(), right.clone())
-                .map(|(_, _, _, thickness)| thickness)
+            + zones
+                .iter()
+                .flat_map(|z| z.windows.iter())
+                .map(|(_, slot, _)| slot_thickness(slot, anchor))
                 .max()
                 .unwrap_or(0) as f64) as i32;
         let old_actual = self.actual_size;
@@ -253,27 +233,21 @@ impl PanelSpace {
         .to_logical(self.scale)
         .to_i32_round();
 
-        let actual_size_constrained = self.constrain_dim(self.actual_size, Some(gap as u32));
-        if self.config.is_horizontal() {
-            self.actual_size.h = actual_size_constrained.h;
-        } else {
-            self.actual_size.w = actual_size_constrained.w;
-        }
+        self.actual_size = self.constrain_dim(self.actual_size, Some(gap as u32));
 
         let (new_logical_length, new_logical_thickness) = if self.config.is_horizontal() {
             (self.actual_size.w, self.actual_size.h)
         } else {
             (self.actual_size.h, self.actual_size.w)
         };
-        let new_dim = if self.config.is_horizontal() {
-            let mut dim = actual_size_constrained;
-            dim.h += gap as i32;
-            dim
+        // the surface is grown on the side facing away from the edge to fit the shadow
+        let shadow_extent = self.config.get_shadow_extent() as i32;
+        let mut new_dim = self.constrain_surface_dim(self.actual_size, Some(gap as u32));
+        if self.config.is_horizontal() {
+            new_dim.h = self.actual_size.h + gap as i32 + shadow_extent;
         } else {
-            let mut dim = actual_size_constrained;
-            dim.w += gap as i32;
-            dim
-        };
+            new_dim.w = self.actual_size.w + gap as i32 + shadow_extent;
+        }
         // update input region of panel when list changes
         let (input_region, layer) = match (self.input_region.as_ref(), self.layer.as_ref()) {
             (Some(r), Some(layer)) => (r, layer),
@@ -289,25 +263,27 @@ impl PanelSpace {
             || new_list_thickness_dim != list_thickness
             || self.animate_state.is_some();
 
-        let left_sum = left_sum_scaled / self.scale;
-        let center_sum = center_sum_scaled / self.scale;
-        let right_sum = right_sum_scaled / self.scale;
-
+        // panels take up as much of the edge as they may
+        let max_length = max_bar_length.min(new_list_dim_length);
         let container_length = if let Some(anim_state) = self.animate_state.as_ref() {
             (new_logical_length as f32
-                + (new_list_dim_length - new_logical_length) as f32 * anim_state.cur.expanded)
+                + (max_length - new_logical_length) as f32 * anim_state.cur.expanded)
                 as i32
         } else if is_dock {
             new_logical_length
         } else {
-            new_list_dim_length
+            max_length
         };
         self.container_length = container_length;
-        let container_lengthwise_pos = (new_list_dim_length - container_length) / 2;
+        let container_lengthwise_pos = self
+            .config
+            .alignment
+            .position(new_list_dim_length, container_length);
 
         if self.panel_changed {
             {
                 let gap = self.gap() as f64 * self.scale;
+                let shadow_extent = shadow_extent as f64 * self.scale;
                 let border_radius = self.border_radius() as f64 * self.scale;
 
                 let mut panel_size = self.actual_size.to_f64().to_physical(self.scale);
@@ -329,8 +305,8 @@ impl PanelSpace {
                 };
                 let loc = match self.config.anchor {
                     PanelAnchor::Left => [gap as f32, container_lengthwise_pos as f32],
-                    PanelAnchor::Right => [0., container_lengthwise_pos as f32],
-                    PanelAnchor::Top => [container_lengthwise_pos as f32, 0.],
+                    PanelAnchor::Right => [shadow_extent as f32, container_lengthwise_pos as f32],
+                    PanelAnchor::Top => [container_lengthwise_pos as f32, shadow_extent as f32],
                     PanelAnchor::Bottom => [container_lengthwise_pos as f32, gap as f32],
                 };
                 self.panel_rect_settings = RoundedRectangleSettings {
@@ -340,6 +316,29 @@ impl PanelSpace {
                     rad_br: rad_br as f32,
                     loc,
                     rect_size: [panel_size.w as f32, panel_size.h as f32],
+                    gradient: match &self.config.background {
+                        CosmicPanelBackground::LinearGradient { stops, angle } => {
+                            Some(GradientSettings::new(stops, *angle, self.config.opacity))
+                        }
+                        _ => None,
+                    },
+                    border: self.config.border.as_ref().map(|b| {
+                        let mut color = b.color.to_rgba();
+                        color[3] *= self.config.opacity;
+                        BorderSettings {
+                            width: (b.width as f64 * self.scale) as f32,
+                            color,
+                        }
+                    }),
+                    shadow: self.config.shadow.as_ref().map(|s| ShadowSettings {
+                        blur: (s.blur_radius as f64 * self.scale) as f32,
+                        // y points up in the shader
+                        offset: [
+                            (s.offset.0 as f64 * self.scale) as f32,
+                            (-s.offset.1 as f64 * self.scale) as f32,
+                        ],
+                        color: s.color.to_rgba(),
+                    }),
                 };
             }
             input_region.subtract(
@@ -349,24 +348,32 @@ impl PanelSpace {
                 self.dimensions.h.max(new_dim.h),
             );
 
-            if is_dock {
-                let (layer_length, actual_length) = if self.config.is_horizontal() {
-                    (new_dim.w, self.actual_size.w)
-                } else {
-                    (new_dim.h, self.actual_size.h)
-                };
-                let side = (layer_length as u32 - actual_length as u32) / 2;
-
-                let (loc, size) = if self.config.is_horizontal() {
-                    ((side as i32, 0), (self.actual_size.w, new_dim.h))
-                } else {
-                    ((0, side as i32), (new_dim.w, self.actual_size.h))
-                };
-
-                input_region.add(loc.0, loc.1, size.0, size.1);
+            let input_length = if is_dock {
+                new_logical_length
             } else {
-                input_region.add(0, 0, new_dim.w, new_dim.h);
-            }
+                max_length
+            };
+            let side = self
+                .config
+                .alignment
+                .position(new_list_dim_length, input_length);
+            // clicks on the shadow pass through
+            let shadow_start = match anchor {
+                PanelAnchor::Bottom | PanelAnchor::Right => shadow_extent,
+                PanelAnchor::Top | PanelAnchor::Left => 0,
+            };
+            let (loc, size) = if self.config.is_horizontal() {
+                (
+                    (side, shadow_start),
+                    (input_length, new_dim.h - shadow_extent),
+                )
+            } else {
+                (
+                    (shadow_start, side),
+                    (new_dim.w - shadow_extent, input_length),
+                )
+            };
+            input_region.add(loc.0, loc.1, size.0, size.1);
             layer
                 .wl_surface()
                 .set_input_region(Some(input_region.wl_region()));
@@ -377,20 +384,6 @@ impl PanelSpace {
         fn center_in_bar(crosswise_dim: u32, dim: u32) -> i32 {
             (crosswise_dim as i32 - dim as i32) / 2
         }
-        // eq length should assign space evenly to all lists even if they are empty
-        let requested_eq_length: f64 = container_length as f64 / 3.;
-        let center_left_spacing = if left_sum < requested_eq_length as f64
-            && center_sum < requested_eq_length as f64
-            && right_sum < requested_eq_length as f64
-        {
-            let center_spacing = (requested_eq_length as f64 - center_sum) / 2.0;
-            let left_spacing = requested_eq_length as f64 - left_sum - padding_u32 as f64;
-            left_spacing + center_spacing
-        } else {
-            (container_length as f64 - left_sum - center_sum - right_sum - 2. * padding_u32 as f64)
-                as f64
-                / 2.
-        };
         if new_list_thickness_dim != list_thickness {
             self.pending_dimensions = Some(new_dim);
             self.is_dirty = true;
@@ -398,18 +391,19 @@ impl PanelSpace {
         }
         // offset for centering
         let margin_offset = match anchor {
-            PanelAnchor::Top | PanelAnchor::Left => gap,
-            PanelAnchor::Bottom | PanelAnchor::Right => 0,
-        } as i32;
-        let mut map_windows = |windows: IterMut<'_, (usize, Window, Option<u32>)>,
-                               mut prev|
-         -> f64 {
-            for (i, w, minimize_priority) in windows {
+            PanelAnchor::Top | PanelAnchor::Left => gap as i32,
+            PanelAnchor::Bottom | PanelAnchor::Right => shadow_extent,
+        };
+        let mut placeholders = std::mem::take(&mut self.placeholders);
+        let mut map_windows = |windows: IterMut<'_, (usize, Slot, Option<u32>)>,
+                               spacing: f64,
+                               mut prev: f64| {
+            for (i, slot, minimize_priority) in windows {
                 // XXX this is a hack to get the logical size of the window
                 // TODO improve how this is done
-                let size = w.bbox().size.to_f64().downscale(self.scale);
+                let size = slot.size().to_f64().downscale(self.scale);
 
-                let cur: f64 = prev + spacing_u32 as f64 * *i as f64;
+                let cur: f64 = prev + spacing * *i as f64;
                 let (x, y);
                 match anchor {
                     PanelAnchor::Left | PanelAnchor::Right => {
@@ -423,7 +417,6 @@ impl PanelSpace {
                         );
                         (x, y) = (cur.0 as i32, cur.1 as i32);
                         prev += size.h as f64;
-                        self.space.map_element(w.clone(), (x, y), false);
                     }
                     PanelAnchor::Top | PanelAnchor::Bottom => {
                         let cur = (
@@ -436,9 +429,26 @@ impl PanelSpace {
                         );
                         (x, y) = (cur.0 as i32, cur.1 as i32);
                         prev += size.w as f64;
-                        self.space.map_element(w.clone(), (x, y), false);
                     }
                 };
+                let w = match slot {
+                    Slot::Window(w) => w,
+                    Slot::Reserved(_) => continue,
+                    Slot::Placeholder { name, error, .. } => {
+                        // placeholders are reused, so their buffers keep the same ids
+                        let mut placeholder =
+                            match placeholders.iter().position(|p| p.name == *name) {
+                                Some(i) => placeholders.swap_remove(i),
+                                None => AppletPlaceholder::new(name.clone(), error.clone()),
+                            };
+                        placeholder.error = error.clone();
+                        placeholder
+                            .set_geo(Rectangle::from_loc_and_size((x, y), size.to_i32_round()));
+                        self.placeholders.push(placeholder);
+                        continue;
+                    }
+                };
+                self.space.map_element(w.clone(), (x, y), false);
                 if minimize_priority.is_some() {
                     let new_rect = Rectangle {
                         loc: (x, y).into(),
@@ -450,7 +460,10 @@ impl PanelSpace {
                         _ = self.panel_tx.send(crate::PanelCalloopMsg::MinimizeRect {
                             output,
                             applet_info: MinimizeApplet {
-                                priority: if is_dock { 1 } else { 0 },
+                                priority: match self.config.kind() {
+                                    PanelKind::Panel => 0,
+                                    PanelKind::Dock(_) | PanelKind::Floating(_) => 1,
+                                },
                                 rect: new_rect,
                                 surface: layer.wl_surface().clone(),
                             },
@@ -458,22 +471,129 @@ impl PanelSpace {
                     }
                 }
             }
-            prev
         };
-        let mut prev: f64 = container_lengthwise_pos as f64 + padding_u32 as f64;
-
-        prev = map_windows(windows_left.iter_mut(), prev);
-        // will be already offset if dock
-        prev += center_left_spacing;
-        map_windows(windows_center.iter_mut(), prev);
 
-        let prev = container_lengthwise_pos as f64 + container_length as f64
-            - padding_u32 as f64
-            - right_sum;
+        let start = container_lengthwise_pos as f64 + padding_u32 as f64;
+        let end = (container_lengthwise_pos + container_length) as f64 - padding_u32 as f64;
+        let spacing = spacing_u32 as f64;
+        let group_length = |alignment: ZoneAlignment| {
+            let group = zones.iter().filter(|z| z.alignment == alignment);
+            group.clone().map(|z| z.length).sum::<f64>()
+                + spacing * group.count().saturating_sub(1) as f64
+        };
+        let start_length = group_length(ZoneAlignment::Start);
+        let center_length = group_length(ZoneAlignment::Center);
+        let end_length = group_length(ZoneAlignment::End);
+
+        // center zones are centered on the panel if they fit between the start and end zones,
+        // otherwise they are centered in the space that is left
+        let free_start = start + start_length + if start_length > 0. { spacing } else { 0. };
+        let free_end = end - end_length - if end_length > 0. { spacing } else { 0. };
+        let mut center_pos = (start + end - center_length) / 2.;
+        if center_pos < free_start || center_pos + center_length > free_end {
+            center_pos = free_start + (free_end - free_start - center_length) / 2.;
+        }
 
-        map_windows(windows_right.iter_mut(), prev);
+        let mut start_pos = start;
+        let mut end_pos = end - end_length;
+        for zone in &mut zones {
+            let pos = match zone.alignment {
+                ZoneAlignment::Start => &mut start_pos,
+                ZoneAlignment::Center => &mut center_pos,
+                ZoneAlignment::End => &mut end_pos,
+                ZoneAlignment::Fraction(f) => {
+                    let pos = container_lengthwise_pos as f64
+                        + f.clamp(0., 1.) as f64 * container_length as f64
+                        - zone.length / 2.;
+                    map_windows(
+                        zone.windows.iter_mut(),
+                        zone.spacing,
+                        pos.min(end - zone.length).max(start),
+                    );
+                    continue;
+                }
+            };
+            map_windows(zone.windows.iter_mut(), zone.spacing, *pos);
+            *pos += zone.length + spacing;
+        }
+        self.hovered_placeholders
+            .retain(|(name, _)| self.placeholders.iter().any(|p| p.name == *name));
         self.space.refresh();
 
         Ok(())
     }
 }
+
+/// what takes up the place of an applet in a zone
+#[derive(Debug)]
+enum Slot {
+    Window(Window),
+    /// the last size of an applet which has no window, while it is restarted
+    Reserved(Size<i32, Logical>),
+    /// an applet which failed
+    Placeholder {
+        name: String,
+        error: String,
+        size: Size<i32, Logical>,
+    },
+}
+
+impl Slot {
+    /// size in the same units as the bounding box of a window
+    fn size(&self) -> Size<i32, Logical> {
+        match self {
+            Slot::Window(w) => w.bbox().size,
+            Slot::Reserved(size) | Slot::Placeholder { size, .. } => *size,
+        }
+    }
+}
+
+/// windows of an applet zone and the space they take up
+struct ZoneLayout {
+    alignment: ZoneAlignment,
+    /// logical space between the windows
+    spacing: f64,
+    /// logical length of the zone
+    length: f64,
+    length_scaled: f64,
+    windows: Vec<(usize, Slot, Option<u32>)>,
+}
+
+/// relative position of the zones with an alignment along the edge
+/// zones with the same alignment are positioned in the order of the config
+fn edge_order(alignment: ZoneAlignment) -> f32 {
+    match alignment {
+        ZoneAlignment::Start => 0.,
+        ZoneAlignment::Center => 0.5,
+        ZoneAlignment::End => 1.,
+        ZoneAlignment::Fraction(f) => f.clamp(0., 1.),
+    }
+}
+
+fn zone_length_scaled(
+    windows: &[(usize, Slot, Option<u32>)],
+    spacing_scaled: f64,
+    anchor: PanelAnchor,
+) -> f64 {
+    windows
+        .iter()
+        .map(|(_, slot, _)| slot_length(slot, anchor))
+        .sum::<i32>() as f64
+        + spacing_scaled * windows.len().saturating_sub(1) as f64
+}
+
+fn slot_length(slot: &Slot, anchor: PanelAnchor) -> i32 {
+    let bbox = slot.size();
+    match anchor {
+        PanelAnchor::Left | PanelAnchor::Right => bbox.h,
+        PanelAnchor::Top | PanelAnchor::Bottom => bbox.w,
+    }
+}
+
+fn slot_thickness(slot: &Slot, anchor: PanelAnchor) -> i32 {
+    let bbox = slot.size();
+    match anchor {
+        PanelAnchor::Left | PanelAnchor::Right => bbox.w,
+        PanelAnchor::Top | PanelAnchor::Bottom => bbox.h,
+    }
+}
@@ -8,11 +8,4 @@ mod popup;
 mod render;
 mod wrapper_space;
 
-pub(crate) use panel_space::{AppletMsg, PanelSpace};
-
-#[derive(Debug)]
-pub enum Alignment {
-    Left,
-    Center,
-    Right,
-}
+pub(crate) use panel_space::{AdoptedApplets, AppletAutoClickAnchor, AppletMsg, PanelSpace};
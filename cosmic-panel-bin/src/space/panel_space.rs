@@ -3,7 +3,10 @@ use std::{
     os::{fd::OwnedFd, unix::net::UnixStream},
     rc::Rc,
     str::FromStr,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
     time::{Duration, Instant},
 };
 
@@ -38,7 +41,9 @@ use smithay::{
         renderer::{
             damage::OutputDamageTracker,
             element::{
-                surface::WaylandSurfaceRenderElement, Element, RenderElement, UnderlyingStorage,
+                solid::{SolidColorBuffer, SolidColorRenderElement},
+                surface::WaylandSurfaceRenderElement,
+                Element, Kind, RenderElement, UnderlyingStorage,
             },
             gles::{GlesError, GlesFrame, GlesRenderer},
             Bind, Unbind,
@@ -50,13 +55,13 @@ use smithay::{
         wayland_protocols::xdg::shell::client::xdg_positioner::{Anchor, Gravity},
         wayland_server::{backend::ClientId, Client, DisplayHandle},
     },
-    utils::{Buffer, Logical, Physical, Rectangle, Size},
+    utils::{Buffer, Logical, Physical, Point, Rectangle, Scale, Size},
     wayland::{
         seat::WaylandFocus,
         shell::xdg::{PopupSurface, PositionerState},
     },
 };
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::mpsc;
 use tracing::{error, info};
 use wayland_egl::WlEglSurface;
 use wayland_protocols::wp::{
@@ -71,29 +76,48 @@ use xdg_shell_wrapper::{
     space::{
         ClientEglDisplay, ClientEglSurface, SpaceEvent, Visibility, WrapperPopup, WrapperSpace,
     },
-    util::smootherstep,
     wp_security_context::SecurityContextManager,
 };
 
-use cosmic_panel_config::{CosmicPanelBackground, CosmicPanelConfig, PanelAnchor};
+use cosmic_panel_config::{
+    AppletConfig, AppletZone, CosmicPanelBackground, CosmicPanelConfig, Easing, PanelAnchor,
+    PanelMargins, RestartPolicy,
+};
 
-use crate::PanelCalloopMsg;
+use crate::{
+    applet_registry::{AppletDesktopEntry, AppletRegistry},
+    restart::RestartTracker,
+    PanelCalloopMsg,
+};
 
 use super::corner_element::{
     init_shaders, RoundedRectangleSettings, RoundedRectangleShaderElement,
 };
 
 pub enum AppletMsg {
-    NewProcess(String, Process),
-    NewNotificationsProcess(String, Process, Vec<(String, String)>, Vec<OwnedFd>),
-    NeedNewNotificationFd(oneshot::Sender<OwnedFd>),
-    ClientSocketPair(ClientId),
+    /// start the process of an applet instance of a space
+    NewProcess(String, u64, Process),
+    NewNotificationsProcess(String, u64, Process, Vec<(String, String)>, Vec<OwnedFd>),
+    /// stop the processes of a space
     Cleanup(String),
+    /// stop the process of an applet instance of a space
+    StopApplet(String, u64),
+    /// move the processes of a recreated space to the space replacing it
+    HandOver {
+        from: String,
+        to: String,
+    },
+    /// show the error of a failed applet to the user
+    ShowError {
+        applet: String,
+        error: String,
+    },
 }
 
 pub(crate) enum PanelRenderElement {
     Wayland(WaylandSurfaceRenderElement<GlesRenderer>),
     RoundedRectangle(RoundedRectangleShaderElement),
+    SolidColor(SolidColorRenderElement),
 }
 
 impl Element for PanelRenderElement {
@@ -101,6 +125,7 @@ impl Element for PanelRenderElement {
         match self {
             Self::Wayland(e) => e.id(),
             Self::RoundedRectangle(e) => e.id(),
+            Self::SolidColor(e) => e.id(),
         }
     }
 
@@ -108,6 +133,7 @@ impl Element for PanelRenderElement {
         match self {
             Self::Wayland(e) => e.current_commit(),
             Self::RoundedRectangle(e) => e.current_commit(),
+            Self::SolidColor(e) => e.current_commit(),
         }
     }
 
@@ -115,6 +141,7 @@ impl Element for PanelRenderElement {
         match self {
             Self::Wayland(e) => e.src(),
             Self::RoundedRectangle(e) => e.src(),
+            Self::SolidColor(e) => e.src(),
         }
     }
 
@@ -122,6 +149,7 @@ impl Element for PanelRenderElement {
         match self {
             Self::Wayland(e) => e.geometry(scale),
             Self::RoundedRectangle(e) => e.geometry(scale),
+            Self::SolidColor(e) => e.geometry(scale),
         }
     }
 }
@@ -137,6 +165,7 @@ impl RenderElement<GlesRenderer> for PanelRenderElement {
         match self {
             Self::Wayland(e) => e.draw(frame, src, dst, damage),
             Self::RoundedRectangle(e) => e.draw(frame, src, dst, damage),
+            Self::SolidColor(e) => RenderElement::<GlesRenderer>::draw(e, frame, src, dst, damage),
         }
     }
 
@@ -144,15 +173,39 @@ impl RenderElement<GlesRenderer> for PanelRenderElement {
         match self {
             PanelRenderElement::Wayland(e) => e.underlying_storage(renderer),
             PanelRenderElement::RoundedRectangle(e) => e.underlying_storage(renderer),
+            PanelRenderElement::SolidColor(e) => e.underlying_storage(renderer),
         }
     }
 }
 
 pub type Clients = Arc<Mutex<Vec<PanelClient>>>;
 
+/// running applets of a recreated space, handed over to the space replacing it
+#[derive(Debug)]
+pub struct AdoptedApplets {
+    /// id of the recreated space, which the processes are registered with
+    pub space_id: String,
+    pub zones: Vec<AppletZone>,
+    pub clients: Vec<Clients>,
+    pub windows: Vec<Window>,
+    /// environment of each applet instance in the recreated space
+    pub env: Vec<(u64, Vec<(String, String)>)>,
+}
+
+/// source of the instance ids of applets
+static NEXT_INSTANCE: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn next_instance() -> u64 {
+    NEXT_INSTANCE.fetch_add(1, Ordering::Relaxed)
+}
+
 #[derive(Debug)]
 pub struct PanelClient {
     pub name: String,
+    /// identifies the applet, and its process, while it runs in the panel
+    /// unlike the name, it is unique if the same applet is added more than once
+    pub instance: u64,
+    pub config: AppletConfig,
     pub client: Client,
     pub stream: Option<UnixStream>,
     pub security_ctx: Option<WpSecurityContextV1>,
@@ -162,6 +215,96 @@ pub struct PanelClient {
     pub is_notification_applet: Option<bool>,
     /// If there is an existing popup, this applet with be pressed when hovered.
     pub auto_popup_hover_press: Option<AppletAutoClickAnchor>,
+    pub restart_policy: Option<RestartPolicy>,
+    /// recent crashes of the applet, which decide when it is restarted
+    pub restart_tracker: RestartTracker,
+    pub status: AppletStatus,
+    /// size of the applet's window when it was last laid out
+    pub last_size: Option<Size<i32, Logical>>,
+}
+
+/// state of an applet, which decides what takes its place while it has no window
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum AppletStatus {
+    /// the applet has not mapped a window yet
+    #[default]
+    Starting,
+    /// the applet has mapped a window, the panel is laid out without it if the window goes away
+    Running,
+    /// the applet crashed and will be restarted, its place is kept
+    Restarting,
+    /// the applet exited and will not be restarted
+    Stopped,
+    /// the applet can't be started, or is not restarted because it is crash-looping
+    /// a placeholder showing the error is drawn in its place
+    Failed(String),
+}
+
+impl AppletStatus {
+    /// the applet has no desktop entry
+    pub fn missing(name: &str) -> Self {
+        Self::Failed(format!("No desktop entry was found for {name}"))
+    }
+}
+
+/// drawn in the place of an applet which failed
+#[derive(Debug, Clone)]
+pub struct AppletPlaceholder {
+    pub name: String,
+    pub error: String,
+    /// logical geometry in the panel
+    pub geo: Rectangle<i32, Logical>,
+    badge: SolidColorBuffer,
+    stem: SolidColorBuffer,
+    dot: SolidColorBuffer,
+}
+
+impl AppletPlaceholder {
+    const BADGE_COLOR: [f32; 4] = [0.96, 0.73, 0.18, 1.0];
+    const GLYPH_COLOR: [f32; 4] = [0.12, 0.12, 0.12, 1.0];
+
+    pub fn new(name: String, error: String) -> Self {
+        Self {
+            name,
+            error,
+            geo: Rectangle::default(),
+            badge: SolidColorBuffer::new((0, 0), Self::BADGE_COLOR),
+            stem: SolidColorBuffer::new((0, 0), Self::GLYPH_COLOR),
+            dot: SolidColorBuffer::new((0, 0), Self::GLYPH_COLOR),
+        }
+    }
+
+    /// a warning glyph, an exclamation mark on a badge, filling the geometry
+    pub fn set_geo(&mut self, geo: Rectangle<i32, Logical>) {
+        self.geo = geo;
+        let side = geo.size.w.min(geo.size.h);
+        let width = (side / 8).max(2);
+        self.badge.update(geo.size, Self::BADGE_COLOR);
+        self.stem.update((width, side / 2), Self::GLYPH_COLOR);
+        self.dot.update((width, width), Self::GLYPH_COLOR);
+    }
+
+    pub fn render_elements(&self, scale: f64) -> impl Iterator<Item = PanelRenderElement> + '_ {
+        let side = self.geo.size.w.min(self.geo.size.h);
+        let width = (side / 8).max(2);
+        let x = self.geo.loc.x + (self.geo.size.w - width) / 2;
+        let top = self.geo.loc.y + (self.geo.size.h - side) / 2;
+        [
+            (&self.badge, self.geo.loc),
+            (&self.stem, Point::from((x, top + side / 5))),
+            (&self.dot, Point::from((x, top + side - side / 5 - width))),
+        ]
+        .into_iter()
+        .map(move |(buffer, loc)| {
+            PanelRenderElement::SolidColor(SolidColorRenderElement::from_buffer(
+                buffer,
+                loc.to_f64().to_physical(scale).to_i32_round(),
+                Scale::from(scale),
+                1.0,
+                Kind::Unspecified,
+            ))
+        })
+    }
 }
 
 #[derive(Debug, Default, Clone, Copy)]
@@ -195,9 +338,11 @@ impl FromStr for AppletAutoClickAnchor {
 }
 
 impl PanelClient {
-    pub fn new(name: String, client: Client, stream: Option<UnixStream>) -> Self {
+    pub fn new(config: AppletConfig, client: Client, stream: Option<UnixStream>) -> Self {
         Self {
-            name,
+            name: config.id.clone(),
+            instance: next_instance(),
+            config,
             client,
             stream,
             security_ctx: None,
@@ -206,8 +351,35 @@ impl PanelClient {
             requests_wayland_display: None,
             is_notification_applet: None,
             auto_popup_hover_press: None,
+            restart_policy: None,
+            restart_tracker: RestartTracker::default(),
+            status: AppletStatus::default(),
+            last_size: None,
         }
     }
+
+    /// apply the launch parameters of the applet's desktop entry
+    pub fn apply_desktop_entry(&mut self, entry: &AppletDesktopEntry) {
+        self.exec = entry.exec.clone();
+        self.requests_wayland_display = Some(entry.requests_wayland_display);
+        self.minimize_priority = self.config.minimize_priority.or(entry.minimize_priority);
+        self.auto_popup_hover_press = entry.auto_popup_hover_press;
+        self.is_notification_applet = Some(entry.is_notification_applet);
+        self.restart_policy = self
+            .config
+            .restart
+            .clone()
+            .or_else(|| entry.restart_policy.clone());
+        self.status = if self.exec.is_some() {
+            AppletStatus::Starting
+        } else {
+            AppletStatus::Failed(format!(
+                "The desktop entry of {} has no Exec key: {}",
+                self.name,
+                entry.path.display()
+            ))
+        };
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -226,6 +398,7 @@ pub struct AnimateState {
     started_at: Instant,
     progress: f32,
     duration: Duration,
+    easing: Easing,
 }
 
 /// space for the cosmic panel
@@ -237,10 +410,19 @@ pub(crate) struct PanelSpace {
     pub config: CosmicPanelConfig,
     pub(crate) space: Space<Window>,
     pub(crate) unmapped: Vec<Window>,
+    /// windows hidden because the bar would exceed its maximum length
+    pub(crate) truncated: Vec<Window>,
+    /// drawn in the place of applets which failed
+    pub(crate) placeholders: Vec<AppletPlaceholder>,
+    /// the applet of the placeholder which is hovered by each seat
+    pub(crate) hovered_placeholders: Vec<(String, String)>,
+    /// applets of the space which this space replaces, adopted once its surface is created
+    pub(crate) adopted_applets: Option<AdoptedApplets>,
+    /// whether the applets were handed over to the space replacing this one
+    pub(crate) applets_handed_over: bool,
     pub(crate) damage_tracked_renderer: Option<OutputDamageTracker>,
-    pub(crate) clients_left: Clients,
-    pub(crate) clients_center: Clients,
-    pub(crate) clients_right: Clients,
+    /// clients of each applet zone, in the order of the configured zones
+    pub(crate) clients: Vec<Clients>,
     pub(crate) last_dirty: Option<Instant>,
     // pending size of the panel
     pub(crate) pending_dimensions: Option<Size<i32, Logical>>,
@@ -256,6 +438,7 @@ pub(crate) struct PanelSpace {
     pub(crate) space_event: Rc<Cell<Option<SpaceEvent>>>,
     pub(crate) c_focused_surface: Rc<RefCell<ClientFocus>>,
     pub(crate) c_hovered_surface: Rc<RefCell<ClientFocus>>,
+    pub(crate) applet_registry: Rc<RefCell<AppletRegistry>>,
     pub(crate) s_focused_surface: ServerFocus,
     pub(crate) s_hovered_surface: ServerPtrFocus,
     pub(crate) visibility: Visibility,
@@ -292,23 +475,25 @@ impl PanelSpace {
         config: CosmicPanelConfig,
         c_focused_surface: Rc<RefCell<ClientFocus>>,
         c_hovered_surface: Rc<RefCell<ClientFocus>>,
+        applet_registry: Rc<RefCell<AppletRegistry>>,
         applet_tx: mpsc::Sender<AppletMsg>,
-        mut bg_color: [f32; 4],
+        bg_color: [f32; 4],
         s_display: DisplayHandle,
         security_context_manager: Option<SecurityContextManager>,
         conn: &Connection,
         panel_tx: calloop::channel::SyncSender<PanelCalloopMsg>,
         visibility: Visibility,
     ) -> Self {
-        bg_color[3] = config.opacity;
-
         Self {
             config,
             space: Space::default(),
             unmapped: Vec::new(),
-            clients_left: Default::default(),
-            clients_center: Default::default(),
-            clients_right: Default::default(),
+            truncated: Vec::new(),
+            placeholders: Vec::new(),
+            hovered_placeholders: Vec::new(),
+            adopted_applets: None,
+            applets_handed_over: false,
+            clients: Default::default(),
             last_dirty: Default::default(),
             pending_dimensions: Default::default(),
             space_event: Default::default(),
@@ -326,6 +511,7 @@ impl PanelSpace {
             start_instant: Instant::now(),
             c_focused_surface,
             c_hovered_surface,
+            applet_registry,
             s_focused_surface: Default::default(),
             s_hovered_surface: Default::default(),
             bg_color,
@@ -352,6 +538,17 @@ impl PanelSpace {
         }
     }
 
+    /// check whether the client belongs to one of the applets of the panel
+    pub(crate) fn has_client(&self, client: &ClientId) -> bool {
+        self.clients.iter().any(|clients| {
+            clients
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|c| c.client.id() == *client)
+        })
+    }
+
     pub fn crosswise(&self) -> i32 {
         if self.config.is_horizontal() {
             self.dimensions.h
@@ -509,8 +706,11 @@ impl PanelSpace {
                     Some(d) => d,
                     None => return,
                 };
-                let progress_norm =
-                    smootherstep(progress.as_millis() as f32 / total_t.as_millis() as f32);
+                let progress_norm = self
+                    .config
+                    .get_hide_easing()
+                    .unwrap_or_default()
+                    .ease(progress.as_millis() as f32 / total_t.as_millis() as f32);
                 let handle = self.config.get_hide_handle().unwrap() as i32;
                 self.is_dirty = true;
 
@@ -527,18 +727,19 @@ impl PanelSpace {
                     } else {
                         self.dimensions.w
                     };
+                    let shadow_extent = self.config.get_shadow_extent() as i32;
                     let target = -panel_size + handle;
 
                     let cur_pix = (progress_norm * target as f32) as i32;
-                    let margin = self.config.get_margin() as i32;
+                    let margins = self.config.get_margins();
 
                     if progress > total_t {
                         if self.config.exclusive_zone() {
-                            layer_surface.set_exclusive_zone(panel_size);
+                            layer_surface.set_exclusive_zone(panel_size - shadow_extent);
                         }
                         Self::set_margin(
                             self.config.anchor,
-                            margin,
+                            margins,
                             target,
                             self.additional_gap,
                             layer_surface,
@@ -549,11 +750,12 @@ impl PanelSpace {
                     } else {
                         if prev_margin != cur_pix {
                             if self.config.exclusive_zone() {
-                                layer_surface.set_exclusive_zone(panel_size - cur_pix);
+                                layer_surface
+                                    .set_exclusive_zone(panel_size - shadow_extent - cur_pix);
                             }
                             Self::set_margin(
                                 self.config.anchor,
-                                margin,
+                                margins,
                                 cur_pix,
                                 self.additional_gap,
                                 layer_surface,
@@ -585,8 +787,11 @@ impl PanelSpace {
                     Some(d) => d,
                     None => return,
                 };
-                let progress_norm =
-                    smootherstep(progress.as_millis() as f32 / total_t.as_millis() as f32);
+                let progress_norm = self
+                    .config
+                    .get_hide_easing()
+                    .unwrap_or_default()
+                    .ease(progress.as_millis() as f32 / total_t.as_millis() as f32);
                 let handle = self.config.get_hide_handle().unwrap() as i32;
 
                 if let FocusStatus::LastFocused(_) = cur_hover {
@@ -603,17 +808,18 @@ impl PanelSpace {
                     } else {
                         self.dimensions.w
                     };
+                    let shadow_extent = self.config.get_shadow_extent() as i32;
                     let start = -panel_size + handle;
 
                     let cur_pix = ((1.0 - progress_norm) * start as f32) as i32;
 
                     if progress > total_t {
                         if self.config.exclusive_zone() {
-                            layer_surface.set_exclusive_zone(panel_size);
+                            layer_surface.set_exclusive_zone(panel_size - shadow_extent);
                         }
                         Self::set_margin(
                             self.config.anchor,
-                            self.config.get_margin() as i32,
+                            self.config.get_margins(),
                             0,
                             self.additional_gap,
                             layer_surface,
@@ -623,12 +829,13 @@ impl PanelSpace {
                     } else {
                         if prev_margin != cur_pix {
                             if self.config.exclusive_zone() {
-                                layer_surface.set_exclusive_zone(panel_size - cur_pix);
+                                layer_surface
+                                    .set_exclusive_zone(panel_size - shadow_extent - cur_pix);
                             }
-                            let margin = self.config.get_margin() as i32;
+                            let margins = self.config.get_margins();
                             Self::set_margin(
                                 self.config.anchor,
-                                margin,
+                                margins,
                                 cur_pix,
                                 self.additional_gap,
                                 layer_surface,
@@ -649,28 +856,34 @@ impl PanelSpace {
 
     fn set_margin(
         anchor: PanelAnchor,
-        margin: i32,
+        margins: PanelMargins,
         target: i32,
         additional_gap: i32,
         layer_surface: &LayerSurface,
     ) {
-        info!("Setting margin: {} {} {}", anchor, margin, target);
+        info!("Setting margin: {} {:?} {}", anchor, margins, target);
+        let (start, end) = margins.lengthwise(anchor);
+        let (start, end) = (start as i32, end as i32);
         match anchor {
-            PanelAnchor::Left => {
-                layer_surface.set_margin(margin, 0, margin, target + additional_gap)
-            }
-            PanelAnchor::Right => {
-                layer_surface.set_margin(margin, target + additional_gap, margin, 0)
-            }
-            PanelAnchor::Top => {
-                layer_surface.set_margin(target + additional_gap, margin, 0, margin)
-            }
-            PanelAnchor::Bottom => {
-                layer_surface.set_margin(0, margin, target + additional_gap, margin)
-            }
+            PanelAnchor::Left => layer_surface.set_margin(start, 0, end, target + additional_gap),
+            PanelAnchor::Right => layer_surface.set_margin(start, target + additional_gap, end, 0),
+            PanelAnchor::Top => layer_surface.set_margin(target + additional_gap, end, 0, start),
+            PanelAnchor::Bottom => layer_surface.set_margin(0, end, target + additional_gap, start),
         };
     }
 
+    fn output_dims(&self) -> Option<(u32, u32)> {
+        self.output
+            .as_ref()
+            .and_then(|(_, _, info)| {
+                info.modes
+                    .iter()
+                    .find_map(|m| if m.current { Some(m.dimensions) } else { None })
+            })
+            .map(|(w, h)| (w as u32, h as u32))
+    }
+
+    /// constrain the size of the panel bar
     pub(crate) fn constrain_dim(
         &self,
         size: Size<i32, Logical>,
@@ -679,19 +892,9 @@ impl PanelSpace {
         let mut w: i32 = size.w;
         let mut h: i32 = size.h;
 
-        let output_dims = self
-            .output
-            .as_ref()
-            .and_then(|(_, _, info)| {
-                info.modes
-                    .iter()
-                    .find_map(|m| if m.current { Some(m.dimensions) } else { None })
-            })
-            .map(|(w, h)| (w as u32, h as u32));
-
         let (constrained_w, constrained_h) =
             self.config
-                .get_dimensions(output_dims, self.suggested_length, active_gap);
+                .get_dimensions(self.output_dims(), self.suggested_length, active_gap);
         if let Some(w_range) = constrained_w {
             w = w.clamp(w_range.start as i32, w_range.end as i32 - 1);
         }
@@ -702,22 +905,60 @@ impl PanelSpace {
         (w as i32, h as i32).into()
     }
 
+    /// constrain the size of the layer surface, which always spans the available length of its edge
+    /// and includes the space for the shadow
+    pub(crate) fn constrain_surface_dim(
+        &self,
+        size: Size<i32, Logical>,
+        active_gap: Option<u32>,
+    ) -> Size<i32, Logical> {
+        let gap = active_gap.unwrap_or_else(|| self.config.get_effective_anchor_gap());
+        let mut dim = self.constrain_dim(size, Some(gap + self.config.get_shadow_extent()));
+        let (output_w, output_h) = self.output_dims().unwrap_or_default();
+        if self.config.is_horizontal() {
+            dim.w = self.suggested_length.unwrap_or(output_w) as i32;
+        } else {
+            dim.h = self.suggested_length.unwrap_or(output_h) as i32;
+        }
+        dim
+    }
+
+    /// the maximum length of the panel bar
+    pub(crate) fn max_bar_length(&self) -> i32 {
+        let (constrained_w, constrained_h) =
+            self.config
+                .get_dimensions(self.output_dims(), self.suggested_length, None);
+        let range = if self.config.is_horizontal() {
+            constrained_w
+        } else {
+            constrained_h
+        };
+        range.map_or(0, |r| r.end as i32 - 1)
+    }
+
+    /// redraw the whole panel on the next frame, for changes of its background
+    fn damage_all(&mut self) {
+        self.damage_tracked_renderer = Some(OutputDamageTracker::new(
+            self.dimensions
+                .to_f64()
+                .to_physical(self.scale)
+                .to_i32_round(),
+            1.0,
+            smithay::utils::Transform::Flipped180,
+        ));
+        self.panel_changed = true;
+        self.is_dirty = true;
+    }
+
     fn apply_animation_state(&mut self) {
+        if self.animate_state.is_some() {
+            self.damage_all();
+        }
         if let Some(animation_state) = self.animate_state.as_mut() {
-            self.damage_tracked_renderer = Some(OutputDamageTracker::new(
-                self.dimensions
-                    .to_f64()
-                    .to_physical(self.scale)
-                    .to_i32_round(),
-                1.0,
-                smithay::utils::Transform::Flipped180,
-            ));
-            self.panel_changed = true;
             let progress = (Instant::now()
                 .duration_since(animation_state.started_at)
                 .as_millis() as f32)
                 / animation_state.duration.as_millis() as f32;
-            self.is_dirty = true;
             if progress >= 1.0 {
                 tracing::info!("Animation finished, setting bg_color to end value");
                 self.bg_color = animation_state.end.bg_color;
@@ -726,7 +967,7 @@ impl PanelSpace {
             }
 
             animation_state.progress = progress;
-            let progress = smootherstep(progress);
+            let progress = animation_state.easing.ease(progress);
             let new_cur = AnimatableState {
                 // TODO: blend in perceptual color space?
                 bg_color: [
@@ -775,7 +1016,7 @@ impl PanelSpace {
             if let Some(layer) = self.layer.as_ref() {
                 Self::set_margin(
                     self.config.anchor,
-                    self.config.get_margin() as i32,
+                    self.config.get_margins(),
                     0,
                     self.additional_gap,
                     layer,
@@ -829,16 +1070,19 @@ impl PanelSpace {
                         PanelAnchor::Left | PanelAnchor::Right => width,
                         PanelAnchor::Top | PanelAnchor::Bottom => height,
                     };
+                    // the shadow may overlap windows
+                    let exclusive_zone =
+                        list_thickness as i32 - self.config.get_shadow_extent() as i32;
 
                     if self.config.autohide.is_none() && self.config.exclusive_zone() {
                         self.layer
                             .as_ref()
                             .unwrap()
-                            .set_exclusive_zone(list_thickness as i32);
-                        if self.config.get_effective_anchor_gap() > 0 {
+                            .set_exclusive_zone(exclusive_zone);
+                        if self.config.get_effective_margins() != PanelMargins::default() {
                             Self::set_margin(
                                 self.config.anchor,
-                                self.config.get_effective_anchor_gap() as i32,
+                                self.config.get_effective_margins(),
                                 0,
                                 self.additional_gap,
                                 layer_surface,
@@ -848,11 +1092,11 @@ impl PanelSpace {
                         && matches!(self.visibility, Visibility::Hidden)
                     {
                         if self.config.exclusive_zone() {
-                            layer_surface.set_exclusive_zone(list_thickness as i32);
+                            layer_surface.set_exclusive_zone(exclusive_zone);
                         }
                         Self::set_margin(
                             self.config.anchor,
-                            self.config.get_margin() as i32,
+                            self.config.get_margins(),
                             -(list_thickness as i32)
                                 + self.config.get_hide_handle().unwrap_or_default() as i32,
                             self.additional_gap,
@@ -886,7 +1130,7 @@ impl PanelSpace {
                     error!("Failed to render, error: {:?}", e);
                 }
             } else {
-                for w in &self.unmapped {
+                for w in self.unmapped.iter().chain(&self.truncated) {
                     let output = self.output.as_ref().unwrap().1.clone();
                     w.send_frame(
                         &output,
@@ -935,7 +1179,7 @@ impl PanelSpace {
                     if height <= 0 {
                         height = 1;
                     }
-                    let dim = self.constrain_dim(
+                    let dim = self.constrain_surface_dim(
                         (width as i32, height as i32).into(),
                         Some(self.gap() as u32),
                     );
@@ -1074,7 +1318,7 @@ impl PanelSpace {
                 if height == 0 {
                     height = 1;
                 }
-                let dim = self.constrain_dim(
+                let dim = self.constrain_surface_dim(
                     (width as i32, height as i32).into(),
                     Some(self.gap() as u32),
                 );
@@ -1103,23 +1347,30 @@ impl PanelSpace {
 
     pub fn is_dark(&self, system_is_dark: bool) -> bool {
         match &self.config.background {
-            CosmicPanelBackground::ThemeDefault | CosmicPanelBackground::Color(_) => system_is_dark,
+            CosmicPanelBackground::ThemeDefault
+            | CosmicPanelBackground::Theme(_)
+            | CosmicPanelBackground::Color(_)
+            | CosmicPanelBackground::LinearGradient { .. } => system_is_dark,
             CosmicPanelBackground::Dark => true,
             CosmicPanelBackground::Light => false,
         }
     }
 
     pub fn set_theme_window_color(&mut self, mut color: [f32; 4]) {
-        if let CosmicPanelBackground::ThemeDefault = self.config.background {
-            color[3] = self.config.opacity;
+        // custom colors and gradients don't follow the theme
+        if !self.config.background.follows_theme() {
+            return;
         }
+        color[3] *= self.config.opacity;
         if let Some(animate_state) = self.animate_state.as_mut() {
             animate_state.end.bg_color = color;
+        } else if self.config.animation.theme_duration == 0 {
+            self.damage_all();
         } else {
             let start = AnimatableState {
                 bg_color: self.bg_color,
                 border_radius: self.config.border_radius,
-                expanded: if self.config.expand_to_edges {
+                expanded: if self.config.expand_to_edges() {
                     1.0
                 } else {
                     0.0
@@ -1135,7 +1386,8 @@ impl PanelSpace {
                 cur,
                 started_at: Instant::now(),
                 progress: 0.0,
-                duration: Duration::from_millis(300),
+                duration: self.config.animation.theme_transition(),
+                easing: self.config.animation.easing,
             })
         }
         self.bg_color = color;
@@ -1209,7 +1461,13 @@ impl PanelSpace {
         }
     }
 
-    pub fn update_config(&mut self, config: CosmicPanelConfig, bg_color: [f32; 4], animate: bool) {
+    /// apply a changed config, transitioning to it over `duration`, or immediately if it is zero
+    pub fn update_config(
+        &mut self,
+        config: CosmicPanelConfig,
+        bg_color: [f32; 4],
+        duration: Duration,
+    ) {
         // avoid animating if currently maximized
         if self.maximized {
             return;
@@ -1219,10 +1477,11 @@ impl PanelSpace {
         if config.exclusive_zone != self.config.exclusive_zone {
             if let Some(l) = self.layer.as_ref() {
                 let list_thickness = if config.exclusive_zone {
-                    match self.config.anchor() {
+                    let thickness = match self.config.anchor() {
                         PanelAnchor::Left | PanelAnchor::Right => self.dimensions.w,
                         PanelAnchor::Top | PanelAnchor::Bottom => self.dimensions.h,
-                    }
+                    };
+                    thickness - config.get_shadow_extent() as i32
                 } else {
                     -1
                 };
@@ -1234,13 +1493,13 @@ impl PanelSpace {
 
         if config.autohide.is_none() && self.config.autohide.is_some() {
             if let Some(l) = self.layer.as_ref() {
-                let margin = config.get_effective_anchor_gap() as i32;
-                Self::set_margin(config.anchor, margin, 0, self.additional_gap, l);
+                let margins = config.get_effective_margins();
+                Self::set_margin(config.anchor, margins, 0, self.additional_gap, l);
                 let list_thickness = match self.config.anchor() {
                     PanelAnchor::Left | PanelAnchor::Right => self.dimensions.w,
                     PanelAnchor::Top | PanelAnchor::Bottom => self.dimensions.h,
                 };
-                l.set_exclusive_zone(list_thickness as i32);
+                l.set_exclusive_zone(list_thickness - config.get_shadow_extent() as i32);
                 let (width, height) = if self.config.is_horizontal() {
                     (0, self.dimensions.h)
                 } else {
@@ -1250,10 +1509,10 @@ impl PanelSpace {
                 needs_commit = true;
             }
         } else {
-            if self.config.get_effective_anchor_gap() != config.get_effective_anchor_gap() {
+            if self.config.get_effective_margins() != config.get_effective_margins() {
                 if let Some(l) = self.layer.as_ref() {
-                    let margin = config.get_effective_anchor_gap() as i32;
-                    Self::set_margin(config.anchor, margin, 0, self.additional_gap, l);
+                    let margins = config.get_effective_margins();
+                    Self::set_margin(config.anchor, margins, 0, self.additional_gap, l);
                     needs_commit = true;
                 }
             }
@@ -1298,11 +1557,11 @@ impl PanelSpace {
             }
         }
 
-        if animate {
+        if !duration.is_zero() {
             let start = AnimatableState {
                 bg_color: self.bg_color,
                 border_radius: self.config.border_radius,
-                expanded: if self.config.expand_to_edges {
+                expanded: if self.config.expand_to_edges() {
                     1.0
                 } else {
                     0.0
@@ -1312,7 +1571,7 @@ impl PanelSpace {
             let end = AnimatableState {
                 bg_color,
                 border_radius: config.border_radius,
-                expanded: if config.expand_to_edges { 1.0 } else { 0.0 },
+                expanded: if config.expand_to_edges() { 1.0 } else { 0.0 },
                 gap: config.get_effective_anchor_gap() as u16,
             };
             if let Some(animated_state) = self.animate_state.as_mut() {
@@ -1320,6 +1579,8 @@ impl PanelSpace {
                 animated_state.end = end;
                 animated_state.started_at = Instant::now();
                 animated_state.progress = 0.0;
+                animated_state.duration = duration;
+                animated_state.easing = config.animation.easing;
             } else {
                 self.animate_state = Some(AnimateState {
                     cur: start.clone(),
@@ -1327,9 +1588,14 @@ impl PanelSpace {
                     end,
                     progress: 0.0,
                     started_at: Instant::now(),
-                    duration: Duration::from_millis(300), // TODO make configurable
+                    duration,
+                    easing: config.animation.easing,
                 });
             }
+        } else {
+            self.animate_state = None;
+            self.bg_color = bg_color;
+            self.panel_changed = true;
         }
 
         self.config = config;
@@ -1346,21 +1612,32 @@ impl PanelSpace {
         if self.maximized == maximized {
             return;
         }
+        let duration = if self.config.autohide.is_none() {
+            config.animation.maximize_transition()
+        } else {
+            Duration::ZERO
+        };
         if !self.maximized {
-            self.update_config(config, bg_color, self.config.autohide.is_none());
+            self.update_config(config, bg_color, duration);
             self.maximized = maximized;
         } else {
             self.maximized = maximized;
-            self.update_config(config, bg_color, self.config.autohide.is_none());
-            if let Some(s) = self.animate_state.as_mut() {
-                s.end.bg_color[3] = self.config.opacity;
-            }
+            self.update_config(config, bg_color, duration);
         }
     }
 }
 
 impl Drop for PanelSpace {
     fn drop(&mut self) {
+        if self.applets_handed_over {
+            return;
+        }
+        // applets which were never adopted are stopped with the space
+        if let Some(applets) = self.adopted_applets.take() {
+            let _ = self
+                .applet_tx
+                .try_send(AppletMsg::Cleanup(applets.space_id));
+        }
         // request processes to stop
         let _ = self.applet_tx.try_send(AppletMsg::Cleanup(self.id()));
     }
@@ -76,32 +76,68 @@ impl PanelSpace {
             }
 
             if let Some((o, _info)) = &self.output.as_ref().map(|(_, o, info)| (o, info)) {
-                let elements: Vec<PanelRenderElement> = (self.panel_changed
-                    && (self.config.anchor_gap || self.config.border_radius > 0))
-                    .then(|| {
-                        PanelRenderElement::RoundedRectangle(RoundedRectangleShader::element(
+                let magnification = self.config.get_magnification() as f64;
+                let panel_rect = (self.panel_changed
+                    && (self.config.anchor_gap
+                        || self.config.border_radius > 0
+                        || self.panel_rect_settings.gradient.is_some()
+                        || self.panel_rect_settings.shadow.is_some()))
+                .then(|| {
+                    PanelRenderElement::RoundedRectangle(RoundedRectangleShader::element(
+                        renderer,
+                        Rectangle::from_loc_and_size((0, 0), dim.to_logical(1)),
+                        self.panel_rect_settings,
+                    ))
+                });
+                // a gradient is drawn below the applets, otherwise the rectangle masks them
+                let (mask, gradient) = if self.panel_rect_settings.gradient.is_some() {
+                    (None, panel_rect)
+                } else {
+                    (panel_rect, None)
+                };
+                // the border and shadow are drawn over everything else
+                let decoration = (self.panel_changed
+                    && (self.panel_rect_settings.border.is_some()
+                        || self.panel_rect_settings.shadow.is_some()))
+                .then(|| {
+                    PanelRenderElement::RoundedRectangle(
+                        RoundedRectangleShader::decoration_element(
                             renderer,
                             Rectangle::from_loc_and_size((0, 0), dim.to_logical(1)),
                             self.panel_rect_settings,
-                        ))
-                    })
+                        ),
+                    )
+                });
+                let elements: Vec<PanelRenderElement> = decoration
                     .into_iter()
+                    .chain(mask)
                     .chain(
                         self.space
                             .elements()
                             .map(|w| {
-                                let loc = self
+                                let mut loc = self
                                     .space
                                     .element_location(w)
                                     .unwrap_or_default()
                                     .to_f64()
-                                    .to_physical(self.scale)
-                                    .to_i32_round();
+                                    .to_physical(self.scale);
+                                let mut scale = self.scale;
+                                // magnify the hovered applet around its center
+                                if magnification != 1.0
+                                    && w.wl_surface().is_some_and(|s| {
+                                        self.s_hovered_surface.iter().any(|f| f.surface == s)
+                                    })
+                                {
+                                    let size = w.bbox().size.to_f64();
+                                    loc.x -= size.w * (magnification - 1.0) / 2.0;
+                                    loc.y -= size.h * (magnification - 1.0) / 2.0;
+                                    scale *= magnification;
+                                }
                                 render_elements_from_surface_tree(
                                     renderer,
                                     w.toplevel().expect("Missing toplevel").wl_surface(),
-                                    loc,
-                                    self.scale,
+                                    loc.to_i32_round(),
+                                    scale,
                                     1.0,
                                     smithay::backend::renderer::element::Kind::Unspecified,
                                 )
@@ -110,6 +146,12 @@ impl PanelSpace {
                             })
                             .flatten(),
                     )
+                    .chain(
+                        self.placeholders
+                            .iter()
+                            .flat_map(|p| p.render_elements(self.scale)),
+                    )
+                    .chain(gradient)
                     .collect();
 
                 _ = my_renderer.render_output(
@@ -1,16 +1,13 @@
 use std::{
     cell::{Cell, RefCell},
-    ffi::OsString,
-    fs,
-    os::{fd::OwnedFd, unix::prelude::AsRawFd},
+    os::unix::prelude::AsRawFd,
     rc::Rc,
     sync::{Arc, Mutex},
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 use anyhow::bail;
-use cosmic_panel_config::{CosmicPanelConfig, CosmicPanelOuput, NAME};
-use freedesktop_desktop_entry::{self, DesktopEntry, Iter};
+use cosmic_panel_config::{AppletZone, CosmicPanelConfig, CosmicPanelOuput, OutputIdentity, NAME};
 use itertools::izip;
 use launch_pad::process::Process;
 use sctk::{
@@ -37,7 +34,8 @@ use smithay::{
     },
     output::Output,
     reexports::wayland_server::{
-        self, protocol::wl_surface::WlSurface as s_WlSurface, DisplayHandle, Resource,
+        self, backend::ClientId, protocol::wl_surface::WlSurface as s_WlSurface, DisplayHandle,
+        Resource,
     },
     utils::{Logical, Rectangle, Size},
     wayland::{
@@ -47,7 +45,6 @@ use smithay::{
         shell::xdg::{PopupSurface, PositionerState, SurfaceCachedState},
     },
 };
-use tokio::sync::oneshot;
 use tracing::{error, error_span, info, info_span, trace};
 use wayland_protocols_wlr::layer_shell::v1::client::zwlr_layer_shell_v1;
 use xdg_shell_wrapper::{
@@ -61,9 +58,15 @@ use xdg_shell_wrapper::{
     wp_viewporter::ViewporterState,
 };
 
-use crate::space::{
-    panel_space::{AppletAutoClickAnchor, PanelClient},
-    AppletMsg,
+use crate::{
+    restart::RestartDecision,
+    space::{
+        panel_space::{
+            next_instance, AdoptedApplets, AppletAutoClickAnchor, AppletStatus, PanelClient,
+        },
+        AppletMsg,
+    },
+    PanelCalloopMsg,
 };
 
 use super::PanelSpace;
@@ -250,372 +253,73 @@ impl WrapperSpace for PanelSpace {
         security_context_manager: Option<SecurityContextManager>,
     ) -> anyhow::Result<()> {
         info!("Spawning applets");
-        let mut left_guard = self.clients_left.lock().unwrap();
-        let mut center_guard = self.clients_center.lock().unwrap();
-        let mut right_guard = self.clients_right.lock().unwrap();
-
-        if left_guard.is_empty() && center_guard.is_empty() && right_guard.is_empty() {
-            *left_guard = self
-                .config
-                .plugins_left()
-                .as_ref()
-                .cloned()
-                .unwrap_or_default()
-                .into_iter()
-                .map(|name| {
-                    let (c, s) = get_client_sock(&mut display);
-                    PanelClient::new(name, c, Some(s))
-                })
-                .collect();
-
-            *center_guard = self
-                .config
-                .plugins_center()
-                .as_ref()
-                .cloned()
-                .unwrap_or_default()
-                .into_iter()
-                .map(|name| {
-                    let (c, s) = get_client_sock(&mut display);
-                    PanelClient::new(name, c, Some(s))
-                })
-                .collect();
-
-            *right_guard = self
+        if self.clients.iter().all(|c| c.lock().unwrap().is_empty()) {
+            self.clients = self
                 .config
-                .plugins_right()
-                .as_ref()
-                .cloned()
-                .unwrap_or_default()
-                .into_iter()
-                .map(|name| {
-                    let (c, s) = get_client_sock(&mut display);
-                    PanelClient::new(name, c, Some(s))
+                .zones
+                .iter()
+                .map(|zone| {
+                    let clients = zone
+                        .applets
+                        .iter()
+                        .filter(|applet| applet.enabled())
+                        .map(|applet| {
+                            let (c, s) = get_client_sock(&mut display);
+                            PanelClient::new(applet.config(), c, Some(s))
+                        })
+                        .collect();
+                    Arc::new(Mutex::new(clients))
                 })
                 .collect();
 
-            let mut desktop_ids: Vec<_> = left_guard
-                .iter_mut()
-                .map(|c| (c, self.clients_left.clone()))
-                .chain(
-                    center_guard
-                        .iter_mut()
-                        .map(|c| (c, self.clients_center.clone())),
-                )
-                .chain(
-                    right_guard
-                        .iter_mut()
-                        .map(|c| (c, self.clients_right.clone())),
-                )
-                .collect();
+            let env_vars = self.applet_env_vars();
+            let mut guards: Vec<_> = self.clients.iter().map(|c| c.lock().unwrap()).collect();
+            let mut panel_clients: Vec<_> = guards.iter_mut().flat_map(|g| g.iter_mut()).collect();
+            info!("{:?}", &panel_clients);
 
-            let config_size = ron::ser::to_string(&self.config.size).unwrap_or_default();
-            let active_output = self
-                .output
-                .as_ref()
-                .and_then(|o| o.2.name.clone())
-                .unwrap_or_default();
-
-            let config_anchor = ron::ser::to_string(&self.config.anchor).unwrap_or_default();
-            let config_bg = ron::ser::to_string(&self.config.background).unwrap_or_default();
-            let config_name = self.config.name.clone();
-            let env_vars = vec![
-                ("COSMIC_PANEL_NAME".to_string(), config_name),
-                ("COSMIC_PANEL_SIZE".to_string(), config_size),
-                ("COSMIC_PANEL_OUTPUT".to_string(), active_output),
-                ("COSMIC_PANEL_ANCHOR".to_string(), config_anchor),
-                ("COSMIC_PANEL_BACKGROUND".to_string(), config_bg),
-                ("RUST_BACKTRACE".to_string(), "1".to_string()),
-            ];
-            info!("{:?}", &desktop_ids);
-
-            let mut max_minimize_priority: u32 = 0;
-
-            let mut panel_clients: Vec<(&mut PanelClient, Arc<Mutex<Vec<PanelClient>>>)> =
-                Vec::new();
-
-            for path in Iter::new(freedesktop_desktop_entry::default_paths()) {
-                // This way each applet is at most started once,
-                // even if multiple desktop files in different directories match
-                if let Some(position) =
-                    desktop_ids
-                        .iter()
-                        .position(|(PanelClient { ref name, .. }, ..)| {
-                            Some(OsString::from(name).as_os_str()) == path.file_stem()
-                        })
-                {
-                    let (panel_client, my_list) = desktop_ids.remove(position);
-                    info!(panel_client.name);
-
-                    if let Ok(bytes) = fs::read_to_string(&path) {
-                        if let Ok(entry) = DesktopEntry::decode(&path, &bytes) {
-                            if let Some(exec) = entry.exec() {
-                                panel_client.exec = Some(exec.to_string());
-                                panel_client.requests_wayland_display =
-                                    Some(entry.desktop_entry("X-HostWaylandDisplay").is_some());
-
-                                panel_client.minimize_priority = if let Some(x_minimize_entry) =
-                                    entry.desktop_entry("X-MinimizeApplet")
-                                {
-                                    match x_minimize_entry.parse::<u32>() {
-                                        Ok(p) => {
-                                            max_minimize_priority = max_minimize_priority.max(p);
-                                            Some(p)
-                                        }
-                                        Err(_) => Some(0),
-                                    }
-                                } else {
-                                    None
-                                };
-
-                                panel_client.auto_popup_hover_press =
-                                    entry.desktop_entry("X-CosmicHoverPopup").map(|v| {
-                                        v.parse::<AppletAutoClickAnchor>().unwrap_or_default()
-                                    });
-
-                                panel_client.is_notification_applet =
-                                    Some(entry.desktop_entry("X-NotificationsApplet").is_some());
-
-                                panel_clients.push((panel_client, my_list));
-                            }
+            {
+                let registry = self.applet_registry.borrow();
+                for panel_client in &mut panel_clients {
+                    match registry.get(&panel_client.name) {
+                        Some(entry) => panel_client.apply_desktop_entry(entry),
+                        None => {
+                            info!("Waiting for the desktop entry of {}", panel_client.name);
+                            panel_client.status = AppletStatus::missing(&panel_client.name);
                         }
                     }
                 }
             }
 
+            let max_minimize_priority = panel_clients
+                .iter()
+                .filter(|panel_client| panel_client.exec.is_some())
+                .filter_map(|panel_client| panel_client.minimize_priority)
+                .max();
             // only allow 1 per panel
             let mut has_minimize = false;
 
-            for (panel_client, my_list) in panel_clients {
-                if !panel_client.exec.is_some() {
+            for panel_client in panel_clients {
+                if panel_client.exec.is_none() {
                     continue;
                 }
-                let Some(socket) = panel_client.stream.take() else {
-                    error!("Failed to get socket for {}", &panel_client.name);
-                    continue;
-                };
 
                 // Ensure there is only one applet per panel with minimize
-                panel_client.minimize_priority = if panel_client
-                    .minimize_priority
-                    .is_some_and(|x| x == max_minimize_priority && !has_minimize)
+                panel_client.minimize_priority = if panel_client.minimize_priority.is_some()
+                    && panel_client.minimize_priority == max_minimize_priority
+                    && !has_minimize
                 {
                     has_minimize = true;
-                    Some(max_minimize_priority)
+                    max_minimize_priority
                 } else {
                     None
                 };
 
-                let is_notification_applet = panel_client.is_notification_applet.unwrap_or(false);
-                let requests_wayland_display =
-                    panel_client.requests_wayland_display.unwrap_or(false);
-
-                let mut exec_iter = Shlex::new(&panel_client.exec.as_deref().unwrap());
-                let exec = exec_iter
-                    .next()
-                    .expect("exec parameter must contain at least on word");
-
-                let mut args = Vec::new();
-                for arg in exec_iter {
-                    trace!("child argument: {}", &arg);
-                    args.push(arg);
-                }
-                let mut fds = Vec::with_capacity(2);
-                let mut applet_env = Vec::new();
-
-                applet_env.push((
-                    "X_MINIMIZE_APPLET".to_string(),
-                    panel_client.minimize_priority.is_some().to_string(),
-                ));
-
-                if requests_wayland_display {
-                    if let Some(security_context_manager) = security_context_manager.as_ref() {
-                        match security_context_manager.create_listener::<W>(qh) {
-                            Ok(security_context) => {
-                                security_context.set_sandbox_engine(NAME.to_string());
-                                security_context.commit();
-
-                                let data = security_context.data::<SecurityContext>().unwrap();
-                                let privileged_socket = data.conn.lock().unwrap().take().unwrap();
-                                applet_env.push((
-                                    "X_PRIVILEGED_WAYLAND_SOCKET".to_string(),
-                                    privileged_socket.as_raw_fd().to_string(),
-                                ));
-                                fds.push(privileged_socket.into());
-                                panel_client.security_ctx = Some(security_context);
-                            }
-                            Err(why) => {
-                                error!(?why, "Failed to create a listener");
-                            }
-                        }
-                    };
-                }
-
-                for (key, val) in &env_vars {
-                    if !requests_wayland_display && *key == "WAYLAND_DISPLAY" {
-                        continue;
-                    }
-                    applet_env.push((key.clone(), val.clone()));
-                }
-                applet_env.push(("WAYLAND_SOCKET".to_string(), socket.as_raw_fd().to_string()));
-
-                fds.push(socket.into());
-                trace!("child: {}, {:?} {:?}", &exec, args, applet_env);
-
-                info!("Starting: {}", exec);
-
-                let display_handle = display.clone();
-                let applet_tx_clone = self.applet_tx.clone();
-                let id_clone = panel_client.name.clone();
-                let id_clone_info = panel_client.name.clone();
-                let id_clone_err = panel_client.name.clone();
-                let client_id = panel_client.client.id();
-                let client_id_info = panel_client.client.id();
-                let client_id_err = panel_client.client.id();
-                let security_context_manager_clone = security_context_manager.clone();
-                let qh_clone = qh.clone();
-
-                let mut process = Process::new()
-                    .with_executable(&exec)
-                    .with_args(args)
-                    .with_on_stderr(move |_, _, out| {
-                        // TODO why is span not included in logs to journald
-                        let id_clone = id_clone_err.clone();
-                        let client_id = client_id_err.clone();
-
-                        async move {
-                            error_span!("stderr", client = ?client_id).in_scope(|| {
-                                error!("{}: {}", id_clone, out);
-                            });
-                        }
-                    })
-                    .with_on_stdout(move |_, _, out| {
-                        let id_clone = id_clone_info.clone();
-                        let client_id = client_id_info.clone();
-                        // TODO why is span not included in logs to journald
-                        async move {
-                            info_span!("stdout", client = ?client_id).in_scope(|| {
-                                info!("{}: {}", id_clone, out);
-                            });
-                        }
-                    })
-                    .with_on_exit(move |mut pman, key, err_code, is_restarting| {
-                        if let Some(err_code) = err_code {
-                            error!("Exited with error code {}", err_code)
-                        }
-                        let my_list = my_list.clone();
-                        let mut display_handle = display_handle.clone();
-                        let id_clone = id_clone.clone();
-                        let applet_tx_clone = applet_tx_clone.clone();
-                        let (c, client_socket) = get_client_sock(&mut display_handle);
-                        let raw_client_socket = client_socket.as_raw_fd();
-                        let client_id_clone = client_id.clone();
-                        let mut applet_env = Vec::with_capacity(1);
-                        let mut fds: Vec<OwnedFd> = Vec::with_capacity(2);
-                        let should_restart = is_restarting && err_code.is_some();
-                        let security_context = if requests_wayland_display && should_restart {
-                            security_context_manager_clone.as_ref().and_then(
-                                |security_context_manager| {
-                                    security_context_manager
-                                        .create_listener(&qh_clone)
-                                        .ok()
-                                        .map(|security_context| {
-                                            security_context.set_sandbox_engine(NAME.to_string());
-                                            security_context.commit();
-
-                                            let data =
-                                                security_context.data::<SecurityContext>().unwrap();
-                                            let privileged_socket =
-                                                data.conn.lock().unwrap().take().unwrap();
-                                            applet_env.push((
-                                                "X_PRIVILEGED_WAYLAND_SOCKET".to_string(),
-                                                privileged_socket.as_raw_fd().to_string(),
-                                            ));
-                                            fds.push(privileged_socket.into());
-                                            security_context
-                                        })
-                                },
-                            )
-                        } else {
-                            None
-                        };
-
-                        async move {
-                            if !should_restart {
-                                _ = pman.stop_process(key).await;
-                                return;
-                            }
-
-                            if is_notification_applet {
-                                let (tx, rx) = oneshot::channel();
-                                _ = applet_tx_clone
-                                    .send(AppletMsg::NeedNewNotificationFd(tx))
-                                    .await;
-                                let Ok(fd) = rx.await else {
-                                    error!("Failed to get new fd");
-                                    return;
-                                };
-                                if let Err(err) = pman
-                                    .update_process_env(
-                                        &key,
-                                        vec![(
-                                            "COSMIC_NOTIFICATIONS".to_string(),
-                                            fd.as_raw_fd().to_string(),
-                                        )],
-                                    )
-                                    .await
-                                {
-                                    error!("Failed to update process env: {}", err);
-                                    return;
-                                }
-                                fds.push(fd);
-                                fds.push(client_socket.into());
-                                if let Err(err) = pman.update_process_fds(&key, move || fds).await {
-                                    error!("Failed to update process fds: {}", err);
-                                    return;
-                                }
-                            } else {
-                                fds.push(client_socket.into());
-                                if let Err(err) = pman.update_process_fds(&key, move || fds).await {
-                                    error!("Failed to update process fds: {}", err);
-                                    return;
-                                }
-                            }
-
-                            if let Some(old_client) = my_list
-                                .lock()
-                                .unwrap()
-                                .iter_mut()
-                                .find(|PanelClient { name, .. }| name == &id_clone)
-                            {
-                                old_client.client = c;
-                                old_client.security_ctx = security_context;
-                                info!("Replaced the client socket");
-                            } else {
-                                error!("Failed to find matching client... {}", &id_clone)
-                            }
-                            let _ = applet_tx_clone
-                                .send(AppletMsg::ClientSocketPair(client_id_clone))
-                                .await;
-                            applet_env.push((
-                                "WAYLAND_SOCKET".to_string(),
-                                raw_client_socket.to_string(),
-                            ));
-                            let _ = pman.update_process_env(&key, applet_env.clone()).await;
-                        }
-                    });
-
-                let msg = if is_notification_applet {
-                    AppletMsg::NewNotificationsProcess(self.id(), process, applet_env, fds)
-                } else {
-                    process = process.with_fds(move || fds);
-
-                    AppletMsg::NewProcess(self.id(), process.with_env(applet_env))
-                };
-                match self.applet_tx.try_send(msg) {
-                    Ok(_) => {}
-                    Err(e) => error!("{e}"),
-                };
+                self.spawn_applet(
+                    panel_client,
+                    qh,
+                    security_context_manager.as_ref(),
+                    &env_vars,
+                );
             }
 
             info!("Done spawning applets");
@@ -726,6 +430,24 @@ impl WrapperSpace for PanelSpace {
     fn handle_button(&mut self, seat_name: &str, press: bool) -> Option<s_WlSurface> {
         self.generated_ptr_event_count = self.generated_ptr_event_count.saturating_sub(1);
 
+        // a press on a placeholder shows why the applet failed
+        if press {
+            if let Some(p) = self
+                .hovered_placeholders
+                .iter()
+                .find(|(_, seat)| seat == seat_name)
+                .and_then(|(name, _)| self.placeholders.iter().find(|p| p.name == *name))
+            {
+                info!("{}: {}", p.name, p.error);
+                if let Err(err) = self.applet_tx.try_send(AppletMsg::ShowError {
+                    applet: p.name.clone(),
+                    error: p.error.clone(),
+                }) {
+                    error!("{err}");
+                }
+            }
+        }
+
         if let Some(prev_foc) = {
             let c_hovered_surface: &ClientFocus = &self.c_hovered_surface.borrow();
 
@@ -764,6 +486,12 @@ impl WrapperSpace for PanelSpace {
         seat_name: &str,
         c_wl_surface: c_wl_surface::WlSurface,
     ) -> Option<ServerPointerFocus> {
+        // the hovered applet may need to be magnified
+        if self.config.get_magnification() != 1.0 {
+            self.is_dirty = true;
+        }
+        self.hovered_placeholders
+            .retain(|(_, seat)| seat != seat_name);
         let mut prev_hover = self
             .s_hovered_surface
             .iter_mut()
@@ -872,19 +600,12 @@ impl WrapperSpace for PanelSpace {
                     && self.generated_ptr_event_count == 0
                 {
                     // send press to new client if it hover flag is set
-                    let left_guard = self.clients_left.lock().unwrap();
-                    let center_guard = self.clients_center.lock().unwrap();
-                    let right_guard = self.clients_right.lock().unwrap();
+                    let guards: Vec<_> = self.clients.iter().map(|c| c.lock().unwrap()).collect();
 
-                    if let Some(c) = left_guard
-                        .iter()
-                        .chain(center_guard.iter())
-                        .chain(right_guard.iter())
-                        .find(|c| {
-                            c.auto_popup_hover_press.is_some()
-                                && Some(c.client.id()) == cur_client_hover_id
-                        })
-                    {
+                    if let Some(c) = guards.iter().flat_map(|g| g.iter()).find(|c| {
+                        c.auto_popup_hover_press.is_some()
+                            && Some(c.client.id()) == cur_client_hover_id
+                    }) {
                         let mut p = (x, y);
                         let effective_anchor = match (
                             c.auto_popup_hover_press.unwrap(),
@@ -987,6 +708,10 @@ impl WrapperSpace for PanelSpace {
                 if let Some((prev_i, _)) = prev_hover {
                     self.s_hovered_surface.swap_remove(prev_i);
                 }
+                if let Some(p) = self.placeholders.iter().find(|p| p.geo.contains((x, y))) {
+                    self.hovered_placeholders
+                        .push((p.name.clone(), seat_name.to_string()));
+                }
                 None
             }
         };
@@ -1009,9 +734,14 @@ impl WrapperSpace for PanelSpace {
 
     fn pointer_leave(&mut self, seat_name: &str, _s: Option<c_wl_surface::WlSurface>) {
         self.generated_ptr_event_count = self.generated_ptr_event_count.saturating_sub(1);
+        if self.config.get_magnification() != 1.0 {
+            self.is_dirty = true;
+        }
 
         self.s_hovered_surface
             .retain(|focus| focus.seat_name != seat_name);
+        self.hovered_placeholders
+            .retain(|(_, seat)| seat != seat_name);
     }
 
     fn pointer_enter(
@@ -1066,7 +796,8 @@ impl WrapperSpace for PanelSpace {
         info: OutputInfo,
     ) -> anyhow::Result<bool> {
         self.output.replace((c_output, s_output, info));
-        self.dimensions = self.constrain_dim(self.dimensions.clone(), Some(self.gap() as u32));
+        self.dimensions =
+            self.constrain_surface_dim(self.dimensions.clone(), Some(self.gap() as u32));
         self.is_dirty = true;
         Ok(true)
     }
@@ -1099,6 +830,11 @@ impl WrapperSpace for PanelSpace {
                 {
                     bail!("output does not match config")
                 }
+                CosmicPanelOuput::Match(matcher)
+                    if !matcher.matches(&OutputIdentity::from(output_info)) =>
+                {
+                    bail!("output does not match config")
+                }
                 _ => {}
             };
             if matches!(self.config.output, CosmicPanelOuput::Active) && self.layer.is_some() {
@@ -1108,7 +844,7 @@ impl WrapperSpace for PanelSpace {
             bail!("output does not match config");
         }
         let dimensions: Size<i32, Logical> =
-            self.constrain_dim((0, 0).into(), Some(self.gap() as u32));
+            self.constrain_surface_dim((0, 0).into(), Some(self.gap() as u32));
 
         let layer = match self.config().layer() {
             zwlr_layer_shell_v1::Layer::Background => Layer::Background,
@@ -1169,7 +905,9 @@ impl WrapperSpace for PanelSpace {
         self.dimensions = dimensions;
         self.space_event = next_render_event;
         self.is_dirty = true;
-        if let Err(err) = self.spawn_clients(
+        if let Some(applets) = self.adopted_applets.take() {
+            self.adopt_applets(applets, &qh);
+        } else if let Err(err) = self.spawn_clients(
             self.s_display.clone().unwrap(),
             &qh,
             self.security_context_manager.clone(),
@@ -1202,16 +940,7 @@ impl WrapperSpace for PanelSpace {
     }
 
     fn get_scale_factor(&self, surface: &s_WlSurface) -> std::option::Option<f64> {
-        let client = surface.client();
-        if self
-            .clients_center
-            .lock()
-            .unwrap()
-            .iter()
-            .chain(self.clients_left.lock().unwrap().iter())
-            .chain(self.clients_right.lock().unwrap().iter())
-            .any(|c| Some(&c.client) == client.as_ref())
-        {
+        if surface.client().is_some_and(|c| self.has_client(&c.id())) {
             Some(self.scale)
         } else {
             None
@@ -1290,3 +1019,563 @@ impl WrapperSpace for PanelSpace {
         std::mem::take(&mut self.generated_pointer_events)
     }
 }
+
+impl PanelSpace {
+    /// environment of the applets, describing the panel
+    fn applet_env_vars(&self) -> Vec<(String, String)> {
+        let config_size = ron::ser::to_string(&self.config.size).unwrap_or_default();
+        let active_output = self
+            .output
+            .as_ref()
+            .and_then(|o| o.2.name.clone())
+            .unwrap_or_default();
+
+        let config_anchor = ron::ser::to_string(&self.config.anchor).unwrap_or_default();
+        let config_bg = ron::ser::to_string(&self.config.background).unwrap_or_default();
+        let config_name = self.config.name.clone();
+        vec![
+            ("COSMIC_PANEL_NAME".to_string(), config_name),
+            ("COSMIC_PANEL_SIZE".to_string(), config_size),
+            ("COSMIC_PANEL_OUTPUT".to_string(), active_output),
+            ("COSMIC_PANEL_ANCHOR".to_string(), config_anchor),
+            ("COSMIC_PANEL_BACKGROUND".to_string(), config_bg),
+            ("RUST_BACKTRACE".to_string(), "1".to_string()),
+        ]
+    }
+
+    /// add the environment describing the panel to the environment of an applet, with the
+    /// applet's overrides applied
+    fn push_panel_env(
+        panel_client: &PanelClient,
+        env_vars: &[(String, String)],
+        applet_env: &mut Vec<(String, String)>,
+    ) {
+        let requests_wayland_display = panel_client.requests_wayland_display.unwrap_or(false);
+        for (key, val) in env_vars {
+            if !requests_wayland_display && *key == "WAYLAND_DISPLAY" {
+                continue;
+            }
+            applet_env.push((key.clone(), val.clone()));
+        }
+        // per applet overrides of the panel's environment
+        let size_override = panel_client.config.size.as_ref().map(|size| {
+            (
+                "COSMIC_PANEL_SIZE".to_string(),
+                ron::ser::to_string(size).unwrap_or_default(),
+            )
+        });
+        for (key, val) in size_override
+            .into_iter()
+            .chain(panel_client.config.env.clone())
+        {
+            if let Some(existing) = applet_env.iter_mut().find(|(k, _)| *k == key) {
+                existing.1 = val;
+            } else {
+                applet_env.push((key, val));
+            }
+        }
+    }
+
+    /// environment describing the panel of each applet instance
+    fn applet_instance_env(&self) -> Vec<(u64, Vec<(String, String)>)> {
+        let env_vars = self.applet_env_vars();
+        self.clients
+            .iter()
+            .flat_map(|list| {
+                list.lock()
+                    .unwrap()
+                    .iter()
+                    .map(|c| {
+                        let mut env = Vec::new();
+                        Self::push_panel_env(c, &env_vars, &mut env);
+                        (c.instance, env)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// start the process of an applet whose desktop entry has been applied
+    fn spawn_applet<W: WrapperSpace>(
+        &self,
+        panel_client: &mut PanelClient,
+        qh: &QueueHandle<GlobalState<W>>,
+        security_context_manager: Option<&SecurityContextManager>,
+        env_vars: &[(String, String)],
+    ) {
+        let Some(socket) = panel_client.stream.take() else {
+            error!("Failed to get socket for {}", &panel_client.name);
+            return;
+        };
+
+        let is_notification_applet = panel_client.is_notification_applet.unwrap_or(false);
+        let requests_wayland_display = panel_client.requests_wayland_display.unwrap_or(false);
+
+        let mut exec_iter = Shlex::new(&panel_client.exec.as_deref().unwrap());
+        let exec = exec_iter
+            .next()
+            .expect("exec parameter must contain at least on word");
+
+        let mut args = Vec::new();
+        for arg in exec_iter {
+            trace!("child argument: {}", &arg);
+            args.push(arg);
+        }
+        args.extend(panel_client.config.args.iter().cloned());
+        let mut fds = Vec::with_capacity(2);
+        let mut applet_env = Vec::new();
+
+        applet_env.push((
+            "X_MINIMIZE_APPLET".to_string(),
+            panel_client.minimize_priority.is_some().to_string(),
+        ));
+
+        if requests_wayland_display {
+            if let Some(security_context_manager) = security_context_manager.as_ref() {
+                match security_context_manager.create_listener::<W>(qh) {
+                    Ok(security_context) => {
+                        security_context.set_sandbox_engine(NAME.to_string());
+                        security_context.commit();
+
+                        let data = security_context.data::<SecurityContext>().unwrap();
+                        let privileged_socket = data.conn.lock().unwrap().take().unwrap();
+                        applet_env.push((
+                            "X_PRIVILEGED_WAYLAND_SOCKET".to_string(),
+                            privileged_socket.as_raw_fd().to_string(),
+                        ));
+                        fds.push(privileged_socket.into());
+                        panel_client.security_ctx = Some(security_context);
+                    }
+                    Err(why) => {
+                        error!(?why, "Failed to create a listener");
+                    }
+                }
+            };
+        }
+
+        Self::push_panel_env(panel_client, env_vars, &mut applet_env);
+        applet_env.push(("WAYLAND_SOCKET".to_string(), socket.as_raw_fd().to_string()));
+
+        fds.push(socket.into());
+        trace!("child: {}, {:?} {:?}", &exec, args, applet_env);
+
+        info!("Starting: {}", exec);
+
+        let panel_tx = self.panel_tx.clone();
+        let instance = panel_client.instance;
+        let id_clone_info = panel_client.name.clone();
+        let id_clone_err = panel_client.name.clone();
+        let client_id_info = panel_client.client.id();
+        let client_id_err = panel_client.client.id();
+
+        let mut process = Process::new()
+            .with_executable(&exec)
+            .with_args(args)
+            .with_on_stderr(move |_, _, out| {
+                // TODO why is span not included in logs to journald
+                let id_clone = id_clone_err.clone();
+                let client_id = client_id_err.clone();
+
+                async move {
+                    error_span!("stderr", client = ?client_id).in_scope(|| {
+                        error!("{}: {}", id_clone, out);
+                    });
+                }
+            })
+            .with_on_stdout(move |_, _, out| {
+                let id_clone = id_clone_info.clone();
+                let client_id = client_id_info.clone();
+                // TODO why is span not included in logs to journald
+                async move {
+                    info_span!("stdout", client = ?client_id).in_scope(|| {
+                        info!("{}: {}", id_clone, out);
+                    });
+                }
+            })
+            .with_on_exit(move |mut pman, key, err_code, is_restarting| {
+                if let Some(err_code) = err_code {
+                    error!("Exited with error code {}", err_code)
+                }
+                let panel_tx = panel_tx.clone();
+                async move {
+                    // launch_pad only restarts processes which were not stopped by the panel,
+                    // the restart is scheduled by the panel following the restart policy instead
+                    if is_restarting {
+                        _ = pman.stop_process(key).await;
+                    }
+                    let _ = panel_tx.send(PanelCalloopMsg::AppletExited {
+                        instance,
+                        crashed: is_restarting && err_code.is_some(),
+                    });
+                }
+            });
+
+        let msg = if is_notification_applet {
+            AppletMsg::NewNotificationsProcess(
+                self.id(),
+                panel_client.instance,
+                process,
+                applet_env,
+                fds,
+            )
+        } else {
+            process = process.with_fds(move || fds);
+
+            AppletMsg::NewProcess(
+                self.id(),
+                panel_client.instance,
+                process.with_env(applet_env),
+            )
+        };
+        match self.applet_tx.try_send(msg) {
+            Ok(_) => {}
+            Err(e) => error!("{e}"),
+        };
+    }
+
+    /// start the applets which were waiting for one of the given desktop entries to be installed
+    pub fn spawn_pending_applets<W: WrapperSpace>(
+        &self,
+        ids: &[String],
+        qh: &QueueHandle<GlobalState<W>>,
+    ) {
+        self.spawn_waiting_applets(qh, |c| ids.contains(&c.name));
+    }
+
+    /// start the applets which were not started yet, and are selected by the filter
+    fn spawn_waiting_applets<W: WrapperSpace>(
+        &self,
+        qh: &QueueHandle<GlobalState<W>>,
+        filter: impl Fn(&PanelClient) -> bool,
+    ) {
+        let env_vars = self.applet_env_vars();
+        let registry = self.applet_registry.borrow();
+        let mut has_minimize = self.clients.iter().any(|list| {
+            list.lock()
+                .unwrap()
+                .iter()
+                .any(|c| c.minimize_priority.is_some())
+        });
+
+        for list in &self.clients {
+            let mut guard = list.lock().unwrap();
+            for panel_client in guard.iter_mut() {
+                // clients which were never started still own their socket
+                if panel_client.exec.is_some()
+                    || panel_client.stream.is_none()
+                    || !filter(panel_client)
+                {
+                    continue;
+                }
+                let Some(entry) = registry.get(&panel_client.name) else {
+                    panel_client.status = AppletStatus::missing(&panel_client.name);
+                    continue;
+                };
+                panel_client.apply_desktop_entry(entry);
+                if panel_client.exec.is_none() {
+                    continue;
+                }
+                if panel_client.minimize_priority.is_some() {
+                    if has_minimize {
+                        panel_client.minimize_priority = None;
+                    }
+                    has_minimize = true;
+                }
+                info!(
+                    "Starting {}, from the desktop entry at {}",
+                    panel_client.name,
+                    entry.path.display()
+                );
+                self.spawn_applet(
+                    panel_client,
+                    qh,
+                    self.security_context_manager.as_ref(),
+                    &env_vars,
+                );
+            }
+        }
+    }
+
+    /// apply changed applet zones to the running panel
+    /// applets which are still configured keep running and only change their place, even if
+    /// they moved to another zone, removed applets are stopped and added applets are started
+    /// applets whose launch parameters changed, like their arguments, are restarted
+    pub fn update_applets<W: WrapperSpace>(
+        &mut self,
+        zones: Vec<AppletZone>,
+        qh: &QueueHandle<GlobalState<W>>,
+    ) {
+        self.config.zones = zones;
+        // the applets are spawned once the panel surface is created
+        if self.layer.is_none() {
+            return;
+        }
+        let Some(mut display) = self.s_display.clone() else {
+            return;
+        };
+
+        // applets are matched by their id in every zone, so moving one keeps it running
+        let mut removed: Vec<PanelClient> = std::mem::take(&mut self.clients)
+            .into_iter()
+            .flat_map(|list| {
+                let mut guard = list.lock().unwrap();
+                std::mem::take(&mut *guard)
+            })
+            .collect();
+        let mut added = Vec::new();
+        let mut restart = Vec::new();
+        let registry = self.applet_registry.borrow();
+        for zone in &self.config.zones {
+            let mut list = Vec::new();
+            for applet in zone.applets.iter().filter(|applet| applet.enabled()) {
+                let config = applet.config();
+                // an instance with unchanged settings is preferred, if the applet is added twice
+                let Some(i) = removed
+                    .iter()
+                    .position(|c| c.config == config)
+                    .or_else(|| removed.iter().position(|c| c.config.id == config.id))
+                else {
+                    let (c, s) = get_client_sock(&mut display);
+                    let panel_client = PanelClient::new(config, c, Some(s));
+                    added.push(panel_client.instance);
+                    list.push(panel_client);
+                    continue;
+                };
+                let mut panel_client = removed.remove(i);
+                if panel_client.config != config {
+                    // these are only read by the applet when it starts
+                    if panel_client.config.args != config.args
+                        || panel_client.config.env != config.env
+                        || panel_client.config.size != config.size
+                        || panel_client.config.minimize_priority != config.minimize_priority
+                    {
+                        restart.push(panel_client.instance);
+                    }
+                    panel_client.config = config;
+                    if let Some(entry) = registry.get(&panel_client.name) {
+                        panel_client.minimize_priority = panel_client
+                            .config
+                            .minimize_priority
+                            .or(entry.minimize_priority);
+                        panel_client.restart_policy = panel_client
+                            .config
+                            .restart
+                            .clone()
+                            .or_else(|| entry.restart_policy.clone());
+                    }
+                }
+                list.push(panel_client);
+            }
+            self.clients.push(Arc::new(Mutex::new(list)));
+        }
+        drop(registry);
+
+        for panel_client in removed {
+            info!(
+                "Stopping {}, it was removed from the panel",
+                panel_client.name
+            );
+            if let Err(err) = self
+                .applet_tx
+                .try_send(AppletMsg::StopApplet(self.id(), panel_client.instance))
+            {
+                error!("{err}");
+            }
+            let client_id = Some(panel_client.client.id());
+            let is_removed = |w: &Window| {
+                w.toplevel()
+                    .expect("Missing toplevel")
+                    .wl_surface()
+                    .client()
+                    .map(|c| c.id())
+                    == client_id
+            };
+            let windows: Vec<_> = self
+                .space
+                .elements()
+                .filter(|w| is_removed(w))
+                .cloned()
+                .collect();
+            for w in windows {
+                self.space.unmap_elem(&w);
+            }
+            self.unmapped.retain(|w| !is_removed(w));
+            self.truncated.retain(|w| !is_removed(w));
+            if self
+                .popups
+                .iter()
+                .any(|p| p.s_surface.wl_surface().client().map(|c| c.id()) == client_id)
+            {
+                self.close_popups();
+            }
+        }
+
+        if !added.is_empty() {
+            self.spawn_waiting_applets(qh, |c| added.contains(&c.instance));
+        }
+        if !restart.is_empty() {
+            self.restart_applets(&restart, qh);
+        }
+        self.is_dirty = true;
+    }
+
+    /// give the running applets to the space replacing this one, so they are not restarted
+    pub fn hand_over_applets(&mut self) -> AdoptedApplets {
+        self.applets_handed_over = true;
+        // the space was replaced again before its surface was created, so it has no applets of
+        // its own yet
+        if let Some(applets) = self.adopted_applets.take() {
+            return applets;
+        }
+        self.close_popups();
+        let mut windows: Vec<_> = self.space.elements().cloned().collect();
+        for w in &windows {
+            self.space.unmap_elem(w);
+        }
+        windows.append(&mut self.unmapped);
+        windows.append(&mut self.truncated);
+        AdoptedApplets {
+            space_id: self.id(),
+            zones: self.config.zones.clone(),
+            env: self.applet_instance_env(),
+            clients: std::mem::take(&mut self.clients),
+            windows,
+        }
+    }
+
+    /// take over the running applets of the space this one replaces, once the output is known
+    /// applets keep running if the new panel gives them the same parameters, e.g. when the panel
+    /// is recreated for changes of other panels or outputs
+    /// applets only read the parameters of the panel from their environment when they start, so
+    /// an applet whose environment changed, e.g. for a new size, anchor or background, is
+    /// restarted in its place
+    fn adopt_applets<W: WrapperSpace>(
+        &mut self,
+        applets: AdoptedApplets,
+        qh: &QueueHandle<GlobalState<W>>,
+    ) {
+        info!("Adopting the applets of {}", applets.space_id);
+        self.clients = applets.clients;
+        // windows which fit the new configuration are mapped by the next layout
+        self.unmapped.extend(applets.windows);
+
+        let restart: Vec<_> = self
+            .applet_instance_env()
+            .into_iter()
+            .filter(|(instance, new_env)| {
+                applets
+                    .env
+                    .iter()
+                    .any(|(i, old_env)| i == instance && old_env != new_env)
+            })
+            .map(|(instance, _)| instance)
+            .collect();
+        if let Err(err) = self.applet_tx.try_send(AppletMsg::HandOver {
+            from: applets.space_id,
+            to: self.id(),
+        }) {
+            error!("{err}");
+        }
+
+        // applets added or removed along with the changes which recreated the panel
+        let zones = std::mem::replace(&mut self.config.zones, applets.zones);
+        if zones != self.config.zones {
+            self.update_applets(zones, qh);
+        }
+        if !restart.is_empty() {
+            self.restart_applets(&restart, qh);
+        }
+        self.is_dirty = true;
+    }
+
+    /// restart applets, which only read the parameters of the panel when they start
+    /// their place in the panel is kept until they map a window again
+    fn restart_applets<W: WrapperSpace>(
+        &self,
+        instances: &[u64],
+        qh: &QueueHandle<GlobalState<W>>,
+    ) {
+        let Some(mut display) = self.s_display.clone() else {
+            return;
+        };
+        let env_vars = self.applet_env_vars();
+        for list in &self.clients {
+            let mut guard = list.lock().unwrap();
+            for panel_client in guard
+                .iter_mut()
+                .filter(|c| instances.contains(&c.instance) && c.exec.is_some())
+            {
+                info!("Restarting {}", panel_client.name);
+                if let Err(err) = self
+                    .applet_tx
+                    .try_send(AppletMsg::StopApplet(self.id(), panel_client.instance))
+                {
+                    error!("{err}");
+                }
+                // the exit of the stopped process must not change the status of the new one
+                panel_client.instance = next_instance();
+                let (c, s) = get_client_sock(&mut display);
+                panel_client.client = c;
+                panel_client.stream = Some(s);
+                panel_client.status = AppletStatus::Restarting;
+                self.spawn_applet(
+                    panel_client,
+                    qh,
+                    self.security_context_manager.as_ref(),
+                    &env_vars,
+                );
+            }
+        }
+    }
+
+    /// restart an applet instance which exited, if it is still in the panel
+    pub fn restart_applet<W: WrapperSpace>(&self, instance: u64, qh: &QueueHandle<GlobalState<W>>) {
+        self.restart_applets(&[instance], qh);
+    }
+
+    /// update the status of an applet instance after its process exited
+    /// returns the client of the exited process and the delay before restarting the applet,
+    /// or None if the instance is not in this panel
+    pub fn applet_exited(
+        &mut self,
+        instance: u64,
+        crashed: bool,
+    ) -> Option<(ClientId, Option<Duration>)> {
+        let mut guards: Vec<_> = self.clients.iter().map(|c| c.lock().unwrap()).collect();
+        let panel_client = guards
+            .iter_mut()
+            .flat_map(|g| g.iter_mut())
+            .find(|c| c.instance == instance)?;
+        let client_id = panel_client.client.id();
+        if !crashed {
+            panel_client.status = AppletStatus::Stopped;
+            drop(guards);
+            self.is_dirty = true;
+            return Some((client_id, None));
+        }
+
+        let policy = panel_client.restart_policy.clone().unwrap_or_default();
+        let delay = match panel_client.restart_tracker.exited(&policy, Instant::now()) {
+            RestartDecision::Restart(delay) => {
+                panel_client.status = AppletStatus::Restarting;
+                Some(delay)
+            }
+            RestartDecision::CrashLoop(retry_after) => {
+                let msg = format!(
+                    "{} is crash-looping, it exited more than {} times within {}s",
+                    panel_client.name, policy.max_restarts, policy.window
+                );
+                error!("{}", msg);
+                match retry_after {
+                    Some(delay) => info!("Retrying {} in {:?}", panel_client.name, delay),
+                    None => info!("Not restarting {} again", panel_client.name),
+                }
+                panel_client.status = AppletStatus::Failed(msg);
+                retry_after
+            }
+        };
+        drop(guards);
+        self.is_dirty = true;
+        Some((client_id, delay))
+    }
+}
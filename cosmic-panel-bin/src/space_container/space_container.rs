@@ -1,8 +1,9 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, path::PathBuf, rc::Rc, time::Duration};
 
 use crate::{
+    applet_registry::AppletRegistry,
     minimize::MinimizeApplet,
-    space::{AppletMsg, PanelSpace},
+    space::{AdoptedApplets, AppletMsg, PanelSpace},
     PanelCalloopMsg,
 };
 use cctk::{
@@ -12,9 +13,9 @@ use cctk::{
 use cosmic_config::CosmicConfigEntry;
 use cosmic_panel_config::{
     CosmicPanelBackground, CosmicPanelConfig, CosmicPanelContainerConfig, CosmicPanelOuput,
-    PanelAnchor,
+    OutputIdentity, PanelAnchor, ThemeToken,
 };
-use cosmic_theme::{Theme, ThemeMode};
+use cosmic_theme::{palette, Theme, ThemeMode};
 use notify::RecommendedWatcher;
 use sctk::{
     output::{self, OutputInfo},
@@ -30,7 +31,7 @@ use smithay::{
     reexports::wayland_server::{self, backend::ClientId},
 };
 use tokio::sync::mpsc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use wayland_server::Resource;
 use xdg_shell_wrapper::{
     client_state::ClientFocus,
@@ -41,6 +42,9 @@ use xdg_shell_wrapper::{
     wp_viewporter::ViewporterState,
 };
 
+/// running applets of a recreated space, with the names of its panel and output
+type RetiredApplets = (String, Option<String>, AdoptedApplets);
+
 pub struct SpaceContainer {
     pub(crate) connection: Option<Connection>,
     pub(crate) config: CosmicPanelContainerConfig,
@@ -49,16 +53,17 @@ pub struct SpaceContainer {
     pub(crate) s_display: Option<wayland_server::DisplayHandle>,
     pub(crate) c_focused_surface: Rc<RefCell<ClientFocus>>,
     pub(crate) c_hovered_surface: Rc<RefCell<ClientFocus>>,
+    pub(crate) applet_registry: Rc<RefCell<AppletRegistry>>,
     pub applet_tx: mpsc::Sender<AppletMsg>,
     pub panel_tx: calloop::channel::SyncSender<PanelCalloopMsg>,
     pub(crate) outputs: Vec<(WlOutput, Output, OutputInfo)>,
-    pub(crate) watchers: HashMap<String, RecommendedWatcher>,
+    pub(crate) watchers: HashMap<String, Vec<RecommendedWatcher>>,
     pub(crate) maximized_toplevels: Vec<(ZcosmicToplevelHandleV1, ToplevelInfo)>,
     pub(crate) toplevels: Vec<(ZcosmicToplevelHandleV1, ToplevelInfo)>,
     pub(crate) workspace_groups: Vec<WorkspaceGroup>,
     pub(crate) is_dark: bool,
-    pub(crate) light_bg: [f32; 4],
-    pub(crate) dark_bg: [f32; 4],
+    pub(crate) light_theme: Theme,
+    pub(crate) dark_theme: Theme,
     pub(crate) security_context_manager: Option<SecurityContextManager>,
     /// map from output name to minimized applet info
     pub(crate) minimized_applets: HashMap<String, MinimizeApplet>,
@@ -84,8 +89,6 @@ impl SpaceContainer {
             .ok()
             .and_then(|c| Theme::get_entry(&c).ok())
             .unwrap_or_else(|| Theme::dark_default());
-        let light = light.background.base;
-        let dark = dark.background.base;
 
         Self {
             connection: None,
@@ -95,6 +98,7 @@ impl SpaceContainer {
             s_display: None,
             c_focused_surface: Default::default(),
             c_hovered_surface: Default::default(),
+            applet_registry: Rc::new(RefCell::new(AppletRegistry::new())),
             applet_tx: tx,
             panel_tx,
             outputs: vec![],
@@ -103,41 +107,120 @@ impl SpaceContainer {
             toplevels: Vec::new(),
             workspace_groups: Vec::new(),
             is_dark,
-            light_bg: [light.red, light.green, light.blue, light.alpha],
-            dark_bg: [dark.red, dark.green, dark.blue, dark.alpha],
+            light_theme: light,
+            dark_theme: dark,
             security_context_manager: None,
             minimized_applets: HashMap::new(),
         }
     }
 
-    pub fn set_dark(&mut self, color: [f32; 4]) {
-        self.dark_bg = color;
+    pub fn set_dark(&mut self, theme: Theme) {
+        self.dark_theme = theme;
+        self.retint_spaces(|s, is_dark| s.is_dark(is_dark));
+    }
 
-        for space in &mut self.space_list {
-            let is_dark = space.is_dark(self.is_dark);
-            if is_dark {
+    pub fn set_light(&mut self, theme: Theme) {
+        self.light_theme = theme;
+        self.retint_spaces(|s, is_dark| !s.is_dark(is_dark));
+    }
+
+    /// update the background of the spaces which follow the theme and match `filter`
+    fn retint_spaces(&mut self, filter: impl Fn(&PanelSpace, bool) -> bool) {
+        let colors = self
+            .space_list
+            .iter()
+            .map(|s| {
+                filter(s, self.is_dark)
+                    .then(|| self.theme_color(&s.config.background))
+                    .flatten()
+            })
+            .collect::<Vec<_>>();
+        for (space, color) in self.space_list.iter_mut().zip(colors) {
+            if let Some(color) = color {
                 space.set_theme_window_color(color);
             }
         }
     }
 
-    pub fn set_light(&mut self, color: [f32; 4]) {
-        self.light_bg = color;
+    pub fn theme(&self, is_dark: bool) -> &Theme {
+        if is_dark {
+            &self.dark_theme
+        } else {
+            &self.light_theme
+        }
+    }
+
+    pub fn theme_bg_color(&self, is_dark: bool) -> [f32; 4] {
+        srgba_to_rgba(self.theme(is_dark).background.base)
+    }
 
-        for space in &mut self.space_list {
-            let is_dark = space.is_dark(self.is_dark);
-            if !is_dark {
-                space.set_theme_window_color(color);
+    pub fn cur_bg_color(&self) -> [f32; 4] {
+        self.theme_bg_color(self.is_dark)
+    }
+
+    /// get the color of a background which follows the theme, without the opacity of the panel
+    pub fn theme_color(&self, background: &CosmicPanelBackground) -> Option<[f32; 4]> {
+        match background {
+            CosmicPanelBackground::ThemeDefault => Some(self.cur_bg_color()),
+            CosmicPanelBackground::Dark => Some(self.theme_bg_color(true)),
+            CosmicPanelBackground::Light => Some(self.theme_bg_color(false)),
+            CosmicPanelBackground::Theme(c) => {
+                let theme = self.theme(self.is_dark);
+                Some(c.resolve(|token| {
+                    srgba_to_rgba(match token {
+                        ThemeToken::Background => theme.background.base,
+                        ThemeToken::PrimaryContainer => theme.primary.base,
+                        ThemeToken::SecondaryContainer => theme.secondary.base,
+                        ThemeToken::Accent => theme.accent.base,
+                    })
+                }))
             }
+            CosmicPanelBackground::Color(_) | CosmicPanelBackground::LinearGradient { .. } => None,
         }
     }
 
-    pub fn cur_bg_color(&self) -> [f32; 4] {
-        if self.is_dark {
-            self.dark_bg.clone()
-        } else {
-            self.light_bg.clone()
+    /// get the properties of an output which are used to match it with the panel configs
+    pub(crate) fn output_identity(&self, info: &OutputInfo) -> OutputIdentity {
+        output_identity(&self.outputs, info)
+    }
+
+    /// get the outputs which a panel is configured to be shown on
+    pub(crate) fn outputs_for(
+        &self,
+        output: &CosmicPanelOuput,
+    ) -> Vec<&(WlOutput, Output, OutputInfo)> {
+        self.outputs
+            .iter()
+            .filter(|(_, _, info)| output.matches(&self.output_identity(info)))
+            .collect()
+    }
+
+    /// get the background color of a panel, with the opacity of the panel applied
+    /// gradients are drawn by the panel itself, on top of a transparent background
+    pub fn bg_color(&self, config: &CosmicPanelConfig) -> [f32; 4] {
+        let mut color = match &config.background {
+            CosmicPanelBackground::Color(c) => c.to_rgba(),
+            CosmicPanelBackground::LinearGradient { .. } => return [0.0; 4],
+            background => self
+                .theme_color(background)
+                .unwrap_or_else(|| self.cur_bg_color()),
+        };
+        color[3] *= config.opacity;
+        color
+    }
+
+    /// get the background color of a maximized panel
+    /// custom colors and gradients are replaced by the theme background, unless the maximized style
+    /// of the panel sets a background
+    pub fn maximized_bg_color(&self, config: &CosmicPanelConfig) -> [f32; 4] {
+        if config.maximized_style.background.is_some() {
+            return self.bg_color(config);
         }
+        let mut color = self
+            .theme_color(&config.background)
+            .unwrap_or_else(|| self.cur_bg_color());
+        color[3] = config.opacity;
+        color
     }
 
     pub fn cleanup_client(&mut self, old_client_id: ClientId) {
@@ -181,12 +264,53 @@ impl SpaceContainer {
         let changed = self.is_dark != is_dark;
         self.is_dark = is_dark;
         if changed {
-            let cur = self.cur_bg_color();
-            for space in &mut self.space_list {
-                if matches!(space.config.background, CosmicPanelBackground::ThemeDefault) {
-                    space.set_theme_window_color(cur);
-                }
-            }
+            // panels using a theme token follow the theme mode too
+            self.retint_spaces(|s, _| {
+                matches!(
+                    s.config.background,
+                    CosmicPanelBackground::ThemeDefault | CosmicPanelBackground::Theme(_)
+                )
+            });
+        }
+    }
+
+    /// re-read changed desktop entries, and start the applets which were waiting for them
+    pub fn desktop_entries_changed<W: WrapperSpace>(
+        &mut self,
+        paths: Vec<PathBuf>,
+        qh: &QueueHandle<GlobalState<W>>,
+    ) {
+        let added = self.applet_registry.borrow_mut().update(&paths);
+        if added.is_empty() {
+            return;
+        }
+        info!("Desktop entries installed: {:?}", added);
+        for space in &mut self.space_list {
+            space.spawn_pending_applets(&added, qh);
+            // the placeholders of the started applets are removed
+            space.is_dirty = true;
+        }
+    }
+
+    /// update the status of an applet instance after its process exited
+    /// returns the delay before the applet is restarted, if it is restarted
+    pub fn applet_exited(&mut self, instance: u64, crashed: bool) -> Option<Duration> {
+        let (client_id, delay) = self
+            .space_list
+            .iter_mut()
+            .find_map(|s| s.applet_exited(instance, crashed))?;
+        self.cleanup_client(client_id);
+        delay
+    }
+
+    /// restart an applet instance which exited
+    pub fn restart_applet<W: WrapperSpace>(
+        &mut self,
+        instance: u64,
+        qh: &QueueHandle<GlobalState<W>>,
+    ) {
+        for space in &self.space_list {
+            space.restart_applet(instance, qh);
         }
     }
 
@@ -208,10 +332,22 @@ impl SpaceContainer {
         qh: &QueueHandle<GlobalState<W>>,
         force_output: Option<WlOutput>,
     ) {
+        let issues = entry.validate();
+        for issue in &issues {
+            if issue.is_error() {
+                error!("Rejecting panel entry: {}", issue);
+            } else {
+                warn!("Panel entry issue: {}", issue);
+            }
+        }
+        if issues.iter().any(|issue| issue.is_error()) {
+            return;
+        }
+
         // if the output is set to "all", we need to check if the config is the same for all outputs
         // if the output is set to a specific output, we need to make sure it doesn't exist on another output
-        let mut output_count = if matches!(entry.output, CosmicPanelOuput::All) {
-            self.outputs.len()
+        let mut output_count = if entry.output.is_per_output() {
+            self.outputs_for(&entry.output).len()
         } else {
             self.space_list
                 .iter()
@@ -221,11 +357,15 @@ impl SpaceContainer {
 
         if !force_output.is_some()
             && self.space_list.iter_mut().any(|s| {
-                let ret = if matches!(entry.output, CosmicPanelOuput::All) {
+                let ret = if entry.output.is_per_output() {
+                    let mut entry = match s.output.as_ref() {
+                        Some((_, _, info)) => {
+                            entry.for_output(&output_identity(&self.outputs, info))
+                        }
+                        None => entry.clone(),
+                    };
                     entry.output = s.config.output.clone();
-                    let ret = s.config == entry;
-                    entry.output = CosmicPanelOuput::All;
-                    ret
+                    s.config == entry
                 } else {
                     s.config == entry
                 };
@@ -247,30 +387,29 @@ impl SpaceContainer {
         };
 
         let output_count_mismatch = match entry.output {
-            CosmicPanelOuput::All => {
+            CosmicPanelOuput::Active => true,
+            CosmicPanelOuput::Name(_) => {
                 self.space_list
                     .iter()
                     .filter(|s| s.config.name == entry.name)
                     .count()
-                    != self.outputs.len()
+                    != 1
             }
-            CosmicPanelOuput::Name(_) => {
+            _ => {
                 self.space_list
                     .iter()
                     .filter(|s| s.config.name == entry.name)
                     .count()
-                    != 1
+                    != self.outputs_for(&entry.output).len()
             }
-            _ => true,
         };
-        let new_priority = entry.get_priority();
-        let (old_priority, old_anchor) = self
+        let old_entry = self
             .config
             .config_list
             .iter()
             .find(|c| c.name == entry.name)
-            .map(|c| (c.get_priority(), c.anchor))
-            .unwrap_or((0, entry.anchor));
+            .cloned();
+        let old_anchor = old_entry.as_ref().map_or(entry.anchor, |c| c.anchor);
 
         let opposite_anchor = if old_anchor == entry.anchor {
             None
@@ -282,67 +421,61 @@ impl SpaceContainer {
                 PanelAnchor::Right => PanelAnchor::Left,
             })
         };
-        // recreate the original if: output changed
-        // or if the output is the same, but the priority changes to conflict with an adjacent panel
-        // or if applet size changes
+        let changes = old_entry.as_ref().map(|old| old.diff(&entry));
+        if let Some(changes) = changes.as_ref() {
+            info!(
+                "changed fields: {:?}, {:?}",
+                changes.fields().collect::<Vec<_>>(),
+                changes.kind()
+            );
+        }
+        // recreate the original if: it is new, the outputs changed,
+        // a changed field can't be applied to the running panel, which includes restarting applets,
+        // or the stacking order changes relative to an adjacent panel
         let must_recreate =
         // implies that there is at least one output which needs to be recreated
         output_count_mismatch
+        || changes.as_ref().map_or(true, |changes| !changes.is_live())
         || self.config.config_list.iter().any(|c| {
-            // size changed
-            c.name == entry.name && c.size != entry.size
-            // output changed
-            || (entry.output != CosmicPanelOuput::All &&
-            (c.name == entry.name && c.output != entry.output))
-            // panel anchor change forces restart
-            || opposite_anchor.is_some()
-            // applet restarts are required
-            || ((c.name == entry.name
-                && (c.is_horizontal() != entry.is_horizontal()
-                || c.size != entry.size
-                || c.background != entry.background
-                || c.plugins_center != entry.plugins_center
-                || c.plugins_wings != entry.plugins_wings)))
-            // Priority change to conflict with adjacent panel
-            || c.name != entry.name
+            c.name != entry.name
                 && Some(c.anchor) != opposite_anchor
-                && ((old_priority < c.get_priority() && new_priority > c.get_priority() || old_priority > c.get_priority() && new_priority < c.get_priority()))}
-            || c.name != entry.name && old_priority != new_priority && c.anchor == entry.anchor
-        );
+                && old_entry.as_ref().map_or(true, |old| old.cmp_priority(c) != entry.cmp_priority(c))
+        });
 
         self.config.config_list.retain(|c| c.name != entry.name);
         self.config.config_list.push(entry.clone());
 
         if !must_recreate {
-            let mut bg_color = match entry.background {
-                CosmicPanelBackground::ThemeDefault => self.cur_bg_color(),
-                CosmicPanelBackground::Dark => self.dark_bg,
-                CosmicPanelBackground::Light => self.light_bg,
-                CosmicPanelBackground::Color(c) => [c[0], c[1], c[2], entry.opacity],
-            };
-            bg_color[3] = entry.opacity;
+            let bg_color = self.bg_color(&entry);
 
             for space in &mut self.space_list {
                 if space.config.name != entry.name {
                     continue;
                 }
 
-                entry.output = space.config.output.clone();
-                space.update_config(entry.clone(), bg_color, true);
+                let mut config = match space.output.as_ref() {
+                    Some((_, _, info)) => entry.for_output(&output_identity(&self.outputs, info)),
+                    None => entry.clone(),
+                };
+                config.output = space.config.output.clone();
+                if config.zones != space.config.zones {
+                    space.update_applets(config.zones.clone(), qh);
+                }
+                space.update_config(config, bg_color, entry.animation.config_transition());
             }
             self.apply_toplevel_changes();
             return;
         }
 
         // remove old one if it exists
-        self.space_list.retain(|s| {
+        let mut retired = self.retire_spaces(|s| {
             // keep if the name is different or the output is different
-            s.config.name != entry.name
+            !(s.config.name != entry.name
                 || force_output.is_some()
                     && s.output
                         .as_ref()
                         .map(|(wl_output, _, _)| Some(wl_output) != force_output.as_ref())
-                        .unwrap_or_default()
+                        .unwrap_or_default())
         });
 
         let outputs: Vec<_> = match &entry.output {
@@ -351,19 +484,16 @@ impl SpaceContainer {
                     entry.clone(),
                     self.c_focused_surface.clone(),
                     self.c_hovered_surface.clone(),
+                    self.applet_registry.clone(),
                     self.applet_tx.clone(),
-                    match entry.background {
-                        CosmicPanelBackground::ThemeDefault => self.cur_bg_color(),
-                        CosmicPanelBackground::Dark => self.dark_bg,
-                        CosmicPanelBackground::Light => self.light_bg,
-                        CosmicPanelBackground::Color(c) => [c[0], c[1], c[1], 1.0],
-                    },
+                    self.bg_color(&entry),
                     self.s_display.clone().unwrap(),
                     self.security_context_manager.clone(),
                     self.connection.as_ref().unwrap(),
                     self.panel_tx.clone(),
                     xdg_shell_wrapper::space::Visibility::Visible,
                 );
+                space.adopted_applets = take_retired(&mut retired, &entry.name, None);
                 if let Err(err) = space.new_output(
                     compositor_state,
                     fractional_scale_manager,
@@ -381,12 +511,20 @@ impl SpaceContainer {
                 }
                 vec![]
             }
-            CosmicPanelOuput::All => self.outputs.iter().collect(),
-            CosmicPanelOuput::Name(name) => self
-                .outputs
-                .iter()
-                .filter(|(_, output, _)| &output.name() == name)
-                .collect(),
+            output => {
+                let identities: Vec<_> = self
+                    .outputs
+                    .iter()
+                    .map(|(_, _, info)| self.output_identity(info))
+                    .collect();
+                // borrow only the outputs, spaces are replaced below
+                self.outputs
+                    .iter()
+                    .zip(identities)
+                    .filter(|(_, identity)| output.matches(identity))
+                    .map(|(o, _)| o)
+                    .collect()
+            }
         };
 
         let maximized_outputs = self.maximized_outputs();
@@ -401,11 +539,17 @@ impl SpaceContainer {
             }
 
             let maximized_output = maximized_outputs.contains(wl_output);
-            let mut configs = self.config.configs_for_output(&output_name);
-            configs.sort_by(|a, b| b.get_priority().cmp(&a.get_priority()));
+            let identity = self.output_identity(info);
+            let mut configs: Vec<_> = self
+                .config
+                .configs_for_output(&identity)
+                .into_iter()
+                .map(|c| c.for_output(&identity))
+                .collect();
+            configs.sort_by(|a, b| b.cmp_priority(a));
             for c in &configs {
                 let is_recreated = c.name == entry.name
-                    || Some(c.anchor) == opposite_anchor && c.get_priority() < new_priority
+                    || Some(c.anchor) == opposite_anchor && c.cmp_priority(&entry).is_lt()
                     || configs.iter().any(|other| {
                         let other_opposite_anchor = match other.anchor {
                             PanelAnchor::Top => PanelAnchor::Bottom,
@@ -413,7 +557,7 @@ impl SpaceContainer {
                             PanelAnchor::Left => PanelAnchor::Right,
                             PanelAnchor::Right => PanelAnchor::Left,
                         };
-                        c.anchor != other_opposite_anchor && c.get_priority() < other.get_priority()
+                        c.anchor != other_opposite_anchor && c.cmp_priority(other).is_lt()
                     });
 
                 if !is_recreated {
@@ -425,29 +569,28 @@ impl SpaceContainer {
                     Visibility::Hidden
                 };
                 // remove old one if it exists
-                self.space_list.retain(|s| {
+                retired.append(&mut self.retire_spaces(|s| {
                     // keep if the name is different or the output is different
-                    s.config.name != c.name
+                    !(s.config.name != c.name
                         || s.output
                             .as_ref()
-                            .is_some_and(|(_, o, _)| o.name() != output_name)
-                });
+                            .is_some_and(|(_, o, _)| o.name() != output_name))
+                }));
                 let mut new_config = (*c).clone();
-                if maximized_output {
+                let bg_color = if maximized_output {
                     new_config.maximize();
-                }
+                    self.maximized_bg_color(&new_config)
+                } else {
+                    self.bg_color(&new_config)
+                };
                 new_config.output = CosmicPanelOuput::Name(output_name.clone());
                 let mut space = PanelSpace::new(
                     new_config.clone(),
                     self.c_focused_surface.clone(),
                     self.c_hovered_surface.clone(),
+                    self.applet_registry.clone(),
                     self.applet_tx.clone(),
-                    match entry.background {
-                        CosmicPanelBackground::ThemeDefault => self.cur_bg_color(),
-                        CosmicPanelBackground::Dark => self.dark_bg,
-                        CosmicPanelBackground::Light => self.light_bg,
-                        CosmicPanelBackground::Color(c) => [c[0], c[1], c[1], 1.0],
-                    },
+                    bg_color,
                     self.s_display.clone().unwrap(),
                     self.security_context_manager.clone(),
                     self.connection.as_ref().unwrap(),
@@ -457,6 +600,7 @@ impl SpaceContainer {
                 if let Some(s_display) = self.s_display.as_ref() {
                     space.set_display_handle(s_display.clone());
                 }
+                space.adopted_applets = take_retired(&mut retired, &c.name, Some(&output_name));
                 if let Err(err) = space.new_output(
                     compositor_state,
                     fractional_scale_manager,
@@ -474,9 +618,35 @@ impl SpaceContainer {
                 }
             }
         }
+        // the applets of panels which were not replaced are stopped
+        for (_, _, applets) in retired {
+            let _ = self
+                .applet_tx
+                .try_send(AppletMsg::Cleanup(applets.space_id));
+        }
         self.apply_toplevel_changes();
     }
 
+    /// remove the spaces matching the filter, keeping their running applets for the spaces
+    /// replacing them
+    /// the spaces are dropped right away, so their surfaces don't overlap the new ones
+    fn retire_spaces(&mut self, filter: impl Fn(&PanelSpace) -> bool) -> Vec<RetiredApplets> {
+        let (mut retired, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.space_list)
+            .into_iter()
+            .partition(|s| filter(s));
+        self.space_list = kept;
+        retired
+            .iter_mut()
+            .map(|s| {
+                (
+                    s.config.name.clone(),
+                    s.output.as_ref().map(|(_, o, _)| o.name()),
+                    s.hand_over_applets(),
+                )
+            })
+            .collect()
+    }
+
     pub fn stacked_spaces_by_priority(
         &mut self,
         output_id: &str,
@@ -495,8 +665,38 @@ impl SpaceContainer {
         if spaces.last().is_some_and(|s| s.config.autohide.is_none()) {
             spaces.remove(spaces.len() - 1);
         }
-        spaces.sort_by(|a, b| a.config.get_priority().cmp(&b.config.get_priority()));
+        spaces.sort_by(|a, b| a.config.cmp_priority(&b.config));
         spaces.reverse();
         spaces
     }
 }
+
+/// get the properties of an output which are used to match it with the panel configs
+/// the first output in the list was advertised first by the compositor
+pub(crate) fn output_identity(
+    outputs: &[(WlOutput, Output, OutputInfo)],
+    info: &OutputInfo,
+) -> OutputIdentity {
+    OutputIdentity {
+        is_first: outputs
+            .first()
+            .is_some_and(|(_, _, first)| first.name == info.name),
+        ..OutputIdentity::from(info)
+    }
+}
+
+fn srgba_to_rgba(c: palette::Srgba) -> [f32; 4] {
+    [c.red, c.green, c.blue, c.alpha]
+}
+
+/// take the applets of a retired space of the panel, on the given output if there is one
+fn take_retired(
+    retired: &mut Vec<RetiredApplets>,
+    name: &str,
+    output: Option<&str>,
+) -> Option<AdoptedApplets> {
+    let i = retired
+        .iter()
+        .position(|(n, o, _)| n == name && (output.is_none() || o.as_deref() == output))?;
+    Some(retired.remove(i).2)
+}
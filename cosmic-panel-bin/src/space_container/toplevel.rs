@@ -7,7 +7,7 @@ use cctk::{
     wayland_client::{protocol::wl_output::WlOutput, Connection},
 };
 
-use cosmic_panel_config::{CosmicPanelBackground, PanelAnchor};
+use cosmic_panel_config::PanelAnchor;
 use itertools::Itertools;
 use sctk::shell::WaylandSurface;
 use xdg_shell_wrapper::{
@@ -137,40 +137,38 @@ impl SpaceContainer {
     }
 
     pub(crate) fn apply_maximized(&mut self, output: &WlOutput, maximized: bool) {
-        let s_list = self
+        let updates = self
             .space_list
-            .iter_mut()
-            .filter(|s| s.output.as_ref().iter().any(|(o, _, _)| o == output));
-        for s in s_list.sorted_by(|a, b| a.config.get_priority().cmp(&b.config.get_priority())) {
-            let c = self
-                .config
-                .config_list
-                .iter()
-                .find(|c| c.name == s.config.name);
-            let mut config = s.config.clone();
-
-            let mut bg_color = if s.is_dark(self.is_dark) {
-                self.dark_bg
-            } else {
-                self.light_bg
-            };
-
-            if maximized {
-                bg_color[3] = 1.0;
-                config.maximize();
-            } else {
-                if let Some(c) = c {
-                    config = c.clone();
-                }
-                bg_color = match config.background {
-                    CosmicPanelBackground::ThemeDefault => bg_color,
-                    CosmicPanelBackground::Dark => self.dark_bg,
-                    CosmicPanelBackground::Light => self.light_bg,
-                    CosmicPanelBackground::Color(c) => [c[0], c[1], c[2], config.opacity],
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.output.as_ref().iter().any(|(o, _, _)| o == output))
+            .sorted_by(|(_, a), (_, b)| a.config.cmp_priority(&b.config))
+            .map(|(i, s)| {
+                let c = self
+                    .config
+                    .config_list
+                    .iter()
+                    .find(|c| c.name == s.config.name);
+                let mut config = s.config.clone();
+
+                let bg_color = if maximized {
+                    config.maximize();
+                    self.maximized_bg_color(&config)
+                } else {
+                    if let Some(c) = c {
+                        config = match s.output.as_ref() {
+                            Some((_, _, info)) => c.for_output(&self.output_identity(info)),
+                            None => c.clone(),
+                        };
+                    }
+                    self.bg_color(&config)
                 };
-            }
+                (i, config, bg_color)
+            })
+            .collect_vec();
 
-            s.set_maximized(maximized, config, bg_color)
+        for (i, config, bg_color) in updates {
+            self.space_list[i].set_maximized(maximized, config, bg_color)
         }
     }
 
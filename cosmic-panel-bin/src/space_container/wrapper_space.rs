@@ -1,6 +1,6 @@
 use std::{cell::RefCell, rc::Rc, time::Instant};
 
-use cosmic_panel_config::{CosmicPanelBackground, CosmicPanelContainerConfig, CosmicPanelOuput};
+use cosmic_panel_config::{CosmicPanelContainerConfig, CosmicPanelOuput};
 use itertools::Itertools;
 use sctk::{
     compositor::CompositorState,
@@ -29,7 +29,7 @@ use xdg_shell_wrapper::{
     wp_viewporter::ViewporterState,
 };
 
-use crate::space::PanelSpace;
+use crate::{space::PanelSpace, PanelCalloopMsg};
 
 use super::SpaceContainer;
 
@@ -77,13 +77,9 @@ impl WrapperSpace for SpaceContainer {
                             config.clone(),
                             self.c_focused_surface.clone(),
                             self.c_hovered_surface.clone(),
+                            self.applet_registry.clone(),
                             self.applet_tx.clone(),
-                            match config.background {
-                                CosmicPanelBackground::ThemeDefault => self.cur_bg_color(),
-                                CosmicPanelBackground::Dark => self.dark_bg,
-                                CosmicPanelBackground::Light => self.light_bg,
-                                CosmicPanelBackground::Color(c) => [c[0], c[1], c[1], 1.0],
-                            },
+                            self.bg_color(config),
                             self.s_display.clone().unwrap(),
                             self.security_context_manager.clone(),
                             conn,
@@ -160,29 +156,27 @@ impl WrapperSpace for SpaceContainer {
         self.outputs
             .push((c_output.clone(), s_output.clone(), output_info.clone()));
 
-        let cur = self.cur_bg_color();
-        let dark = self.dark_bg;
-        let light = self.light_bg;
+        let identity = self.output_identity(&output_info);
+        let configs: Vec<_> = self
+            .config
+            .configs_for_output(&identity)
+            .into_iter()
+            .map(|c| c.for_output(&identity))
+            .collect();
+        let bg_colors: Vec<_> = configs.iter().map(|c| self.bg_color(c)).collect();
         // TODO error handling
         // create the spaces that are configured to use this output, including spaces configured for All
-        let mut new_spaces = self
-            .config
-            .configs_for_output(&output_name)
+        let mut new_spaces = configs
             .into_iter()
-            .filter_map(|config| {
+            .zip(bg_colors)
+            .filter_map(|(config, bg_color)| {
                 let visible = if config.autohide.is_some() {
                     Visibility::Hidden
                 } else {
                     Visibility::Visible
                 };
                 match &config.output {
-                    CosmicPanelOuput::All => {
-                        let c = match config.background {
-                            CosmicPanelBackground::ThemeDefault => cur,
-                            CosmicPanelBackground::Dark => dark,
-                            CosmicPanelBackground::Light => light,
-                            CosmicPanelBackground::Color(c) => [c[0], c[1], c[1], 1.0],
-                        };
+                    output if output.is_per_output() => {
                         let mut s = if let Some(s) = self.space_list.iter_mut().position(|s| {
                             s.config.name == config.name
                                 && Some(&c_output) == s.output.as_ref().map(|o| &o.0)
@@ -193,8 +187,9 @@ impl WrapperSpace for SpaceContainer {
                                 config.clone(),
                                 self.c_focused_surface.clone(),
                                 self.c_hovered_surface.clone(),
+                                self.applet_registry.clone(),
                                 self.applet_tx.clone(),
-                                c,
+                                bg_color,
                                 self.s_display.clone().unwrap(),
                                 self.security_context_manager.clone(),
                                 conn,
@@ -244,13 +239,9 @@ impl WrapperSpace for SpaceContainer {
                                 config.clone(),
                                 self.c_focused_surface.clone(),
                                 self.c_hovered_surface.clone(),
+                                self.applet_registry.clone(),
                                 self.applet_tx.clone(),
-                                match config.background {
-                                    CosmicPanelBackground::ThemeDefault => cur,
-                                    CosmicPanelBackground::Dark => dark,
-                                    CosmicPanelBackground::Light => light,
-                                    CosmicPanelBackground::Color(c) => [c[0], c[1], c[1], 1.0],
-                                },
+                                bg_color,
                                 self.s_display.clone().unwrap(),
                                 self.security_context_manager.clone(),
                                 conn,
@@ -307,16 +298,11 @@ impl WrapperSpace for SpaceContainer {
             .client()
             .map(|c| c.id());
 
-        if let Some(space) = self.space_list.iter_mut().find(|space| {
-            space
-                .clients_center
-                .lock()
-                .unwrap()
-                .iter()
-                .chain(space.clients_left.lock().unwrap().iter())
-                .chain(space.clients_right.lock().unwrap().iter())
-                .any(|c| Some(c.client.id()) == w_client)
-        }) {
+        if let Some(space) = self
+            .space_list
+            .iter_mut()
+            .find(|space| w_client.as_ref().is_some_and(|c| space.has_client(c)))
+        {
             space.add_window(s_top_level);
         }
     }
@@ -336,16 +322,11 @@ impl WrapperSpace for SpaceContainer {
         // add popup to the space with a client that matches the window
         let p_client = s_surface.wl_surface().client().map(|c| c.id());
 
-        if let Some(space) = self.space_list.iter_mut().find(|space| {
-            space
-                .clients_center
-                .lock()
-                .unwrap()
-                .iter()
-                .chain(space.clients_left.lock().unwrap().iter())
-                .chain(space.clients_right.lock().unwrap().iter())
-                .any(|c| Some(c.client.id()) == p_client)
-        }) {
+        if let Some(space) = self
+            .space_list
+            .iter_mut()
+            .find(|space| p_client.as_ref().is_some_and(|c| space.has_client(c)))
+        {
             space.add_popup(
                 compositor_state,
                 fractional_scale_manager,
@@ -371,16 +352,11 @@ impl WrapperSpace for SpaceContainer {
         // add popup to the space with a client that matches the window
         let p_client = popup.wl_surface().client().map(|c| c.id());
 
-        if let Some(space) = self.space_list.iter_mut().find(|space| {
-            space
-                .clients_center
-                .lock()
-                .unwrap()
-                .iter()
-                .chain(space.clients_left.lock().unwrap().iter())
-                .chain(space.clients_right.lock().unwrap().iter())
-                .any(|c| Some(c.client.id()) == p_client)
-        }) {
+        if let Some(space) = self
+            .space_list
+            .iter_mut()
+            .find(|space| p_client.as_ref().is_some_and(|c| space.has_client(c)))
+        {
             space.reposition_popup(popup, positioner_state, token)?
         }
         anyhow::bail!("Failed to find popup with matching client id")
@@ -438,16 +414,11 @@ impl WrapperSpace for SpaceContainer {
         // add window to the space with a client that matches the window
         let w_client = w.client().map(|c| c.id());
 
-        if let Some(space) = self.space_list.iter_mut().find(|space| {
-            space
-                .clients_center
-                .lock()
-                .unwrap()
-                .iter()
-                .chain(space.clients_left.lock().unwrap().iter())
-                .chain(space.clients_right.lock().unwrap().iter())
-                .any(|c| Some(c.client.id()) == w_client)
-        }) {
+        if let Some(space) = self
+            .space_list
+            .iter_mut()
+            .find(|space| w_client.as_ref().is_some_and(|c| space.has_client(c)))
+        {
             space.dirty_window(dh, w);
         }
     }
@@ -460,16 +431,11 @@ impl WrapperSpace for SpaceContainer {
         // add window to the space with a client that matches the window
         let p_client = w.client().map(|c| c.id());
 
-        if let Some(space) = self.space_list.iter_mut().find(|space| {
-            space
-                .clients_center
-                .lock()
-                .unwrap()
-                .iter()
-                .chain(space.clients_left.lock().unwrap().iter())
-                .chain(space.clients_right.lock().unwrap().iter())
-                .any(|c| Some(c.client.id()) == p_client)
-        }) {
+        if let Some(space) = self
+            .space_list
+            .iter_mut()
+            .find(|space| p_client.as_ref().is_some_and(|c| space.has_client(c)))
+        {
             space.dirty_popup(dh, w);
         }
     }
@@ -794,6 +760,25 @@ impl WrapperSpace for SpaceContainer {
         self.outputs.retain(|o| o.0 != c_output);
         self.space_list
             .retain(|s| s.output.as_ref().map(|o| &o.0) != Some(&c_output));
+
+        // another output may match now, e.g. if the first output was removed
+        for config in &self.config.config_list {
+            if !config.output.is_per_output() {
+                continue;
+            }
+            for (wl_output, _, info) in &self.outputs {
+                let has_space = self.space_list.iter().any(|s| {
+                    s.config.name == config.name
+                        && s.output.as_ref().map(|o| &o.0) == Some(wl_output)
+                });
+                if !has_space && config.output.matches(&self.output_identity(info)) {
+                    let _ = self.panel_tx.send(PanelCalloopMsg::RestartSpace(
+                        config.clone(),
+                        wl_output.clone(),
+                    ));
+                }
+            }
+        }
         Ok(())
     }
 
@@ -803,9 +788,13 @@ impl WrapperSpace for SpaceContainer {
         s_output: Output,
         info: OutputInfo,
     ) -> anyhow::Result<bool> {
-        self.outputs.retain(|o| o.0 != c_output);
-        self.outputs
-            .push((c_output.clone(), s_output.clone(), info.clone()));
+        // keep the order of the outputs, which decides the output matching `Primary`
+        if let Some(o) = self.outputs.iter_mut().find(|o| o.0 == c_output) {
+            *o = (c_output.clone(), s_output.clone(), info.clone());
+        } else {
+            self.outputs
+                .push((c_output.clone(), s_output.clone(), info.clone()));
+        }
         let mut found = false;
         for s in &mut self.space_list {
             if s.output.as_ref().map(|o| &o.0) == Some(&c_output) {
@@ -0,0 +1,237 @@
+//! Config for applets in the cosmic panel
+
+use std::{collections::BTreeMap, fmt, str::FromStr, time::Duration};
+
+use serde::{
+    de::{self, value::MapAccessDeserializer},
+    Deserialize, Deserializer, Serialize,
+};
+
+use crate::PanelSize;
+
+/// an applet in the panel, either just its desktop ID, or a desktop ID with extra settings
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum AppletEntry {
+    /// desktop ID of the applet
+    Id(String),
+    /// applet with extra settings
+    Configured(AppletConfig),
+}
+
+/// settings for a single applet
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct AppletConfig {
+    /// desktop ID of the applet
+    pub id: String,
+    /// arguments passed to the applet after the ones in its desktop entry
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// environment variables for the applet, overriding the ones set by the panel
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// size used by the applet instead of the size of the panel
+    #[serde(default)]
+    pub size: Option<PanelSize>,
+    /// whether the applet is started
+    #[serde(default = "enabled_default")]
+    pub enabled: bool,
+    /// priority used instead of the `X-MinimizeApplet` key of the desktop entry
+    #[serde(default)]
+    pub minimize_priority: Option<u32>,
+    /// restart policy used instead of the `X-CosmicRestartPolicy` key of the desktop entry
+    #[serde(default)]
+    pub restart: Option<RestartPolicy>,
+}
+
+fn enabled_default() -> bool {
+    true
+}
+
+impl AppletConfig {
+    pub fn new(id: String) -> Self {
+        Self {
+            id,
+            args: Vec::new(),
+            env: BTreeMap::new(),
+            size: None,
+            enabled: true,
+            minimize_priority: None,
+            restart: None,
+        }
+    }
+}
+
+/// how an applet is restarted after exiting with an error
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Hash)]
+#[serde(deny_unknown_fields, default)]
+pub struct RestartPolicy {
+    /// restarts allowed within `window`, the applet is crash-looping if it exits again
+    pub max_restarts: u32,
+    /// length of the window in seconds
+    pub window: u32,
+    /// delay before restarting in milliseconds, doubled for every earlier restart in the window
+    pub backoff: u32,
+    /// longest delay before restarting in milliseconds
+    pub max_backoff: u32,
+    /// what happens once the applet is crash-looping
+    pub give_up: GiveUpAction,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 5,
+            window: 60,
+            backoff: 100,
+            max_backoff: 5000,
+            give_up: GiveUpAction::Stop,
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// delay before restarting, after the given number of earlier restarts in the window
+    pub fn delay(&self, restarts: u32) -> Duration {
+        let backoff = u64::from(self.backoff) << restarts.min(32);
+        Duration::from_millis(backoff.min(self.max_backoff.into()))
+    }
+
+    pub fn window(&self) -> Duration {
+        Duration::from_secs(self.window.into())
+    }
+}
+
+/// parses the RON value of the `X-CosmicRestartPolicy` desktop entry key,
+/// for example `(max_restarts: 3, give_up: RetryAfter(300))`
+impl FromStr for RestartPolicy {
+    type Err = ron::error::SpannedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ron::from_str(s)
+    }
+}
+
+/// what happens to a crash-looping applet
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(deny_unknown_fields)]
+pub enum GiveUpAction {
+    /// the applet is not restarted again
+    #[default]
+    Stop,
+    /// the applet is restarted once more after the given number of seconds
+    RetryAfter(u32),
+}
+
+impl AppletEntry {
+    /// get the desktop ID of the applet
+    pub fn id(&self) -> &str {
+        match self {
+            AppletEntry::Id(id) => id,
+            AppletEntry::Configured(config) => &config.id,
+        }
+    }
+
+    /// get whether the applet is started
+    pub fn enabled(&self) -> bool {
+        match self {
+            AppletEntry::Id(_) => true,
+            AppletEntry::Configured(config) => config.enabled,
+        }
+    }
+
+    /// get the settings of the applet, with defaults for a plain desktop ID
+    pub fn config(&self) -> AppletConfig {
+        match self {
+            AppletEntry::Id(id) => AppletConfig::new(id.clone()),
+            AppletEntry::Configured(config) => config.clone(),
+        }
+    }
+}
+
+impl From<String> for AppletEntry {
+    fn from(id: String) -> Self {
+        Self::Id(id)
+    }
+}
+
+impl From<&str> for AppletEntry {
+    fn from(id: &str) -> Self {
+        Self::Id(id.to_string())
+    }
+}
+
+// untagged enums can't be deserialized from RON if they contain enums, so the variant is
+// chosen by hand
+impl<'de> Deserialize<'de> for AppletEntry {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct AppletEntryVisitor;
+
+        impl<'de> de::Visitor<'de> for AppletEntryVisitor {
+            type Value = AppletEntry;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a desktop ID or an applet config")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(AppletEntry::Id(v.to_string()))
+            }
+
+            fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+                Ok(AppletEntry::Id(v))
+            }
+
+            fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                AppletConfig::deserialize(MapAccessDeserializer::new(map))
+                    .map(AppletEntry::Configured)
+            }
+        }
+
+        deserializer.deserialize_any(AppletEntryVisitor)
+    }
+}
+
+/// alignment of an applet zone along the edge of the panel
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub enum ZoneAlignment {
+    /// packed at the left / top of the panel
+    Start,
+    /// packed in the middle of the panel, or in the middle of the remaining space if it doesn't fit
+    Center,
+    /// packed at the right / bottom of the panel
+    End,
+    /// centered on a fraction of the length of the panel, between 0.0 and 1.0
+    /// may overlap other zones
+    Fraction(f32),
+}
+
+/// a named group of applets in the panel
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AppletZone {
+    /// name of the zone, should be unique in the panel
+    pub name: String,
+    pub alignment: ZoneAlignment,
+    /// space between the applets of the zone, or None to use the spacing of the panel
+    #[serde(default)]
+    pub spacing: Option<u32>,
+    /// applets in the zone
+    pub applets: Vec<AppletEntry>,
+}
+
+impl AppletZone {
+    pub fn new(name: &str, alignment: ZoneAlignment, applets: Vec<AppletEntry>) -> Self {
+        Self {
+            name: name.to_string(),
+            alignment,
+            spacing: None,
+            applets,
+        }
+    }
+}
@@ -1,7 +1,10 @@
-use crate::{CosmicPanelBackground, CosmicPanelConfig, CosmicPanelOuput};
+use crate::{
+    AnimationConfig, AppletZone, CosmicPanelBackground, CosmicPanelConfig, CosmicPanelOuput,
+    DockConfig, Easing, MaximizedStyle, OutputIdentity, PanelAlignment, PanelKind, ZoneAlignment,
+};
 use cosmic_config::{Config, ConfigGet, ConfigSet, CosmicConfigEntry};
 use serde::{Deserialize, Serialize};
-use tracing::warn;
+use tracing::{error, info, warn};
 use xdg_shell_wrapper_config::{Layer, WrapperConfig, WrapperOutput};
 
 /// Config structure for the cosmic panel
@@ -34,21 +37,24 @@ impl WrapperConfig for CosmicPanelContainerConfig {
 }
 
 pub const NAME: &str = "com.system76.CosmicPanel";
-pub const VERSION: u64 = 1;
+pub const VERSION: u64 = 3;
 
 impl CosmicPanelContainerConfig {
     /// load config with the provided name
     pub fn load() -> Result<Self, (Vec<cosmic_config::Error>, Self)> {
-        let config = match Self::cosmic_config() {
-            Ok(config) => config,
-            Err(e) => {
-                warn!("Falling back to default panel configuration");
-                return Err((vec![e], Self::default()));
-            }
-        };
-        Self::load_from_config(&config, false)
+        match crate::migrate() {
+            Ok(Some(report)) => info!(
+                "Migrated panel config from version {} to {}, backups saved in {:?}",
+                report.from, report.to, report.backups
+            ),
+            Ok(None) => {}
+            Err(e) => error!("Failed to migrate panel config: {}", e),
+        }
+        Self::load_layered()
     }
 
+    /// load a single layer of the config, without falling back to the system layer for
+    /// missing user keys, see [`CosmicPanelContainerConfig::load_layered`]
     pub fn load_from_config(
         config: &Config,
         system: bool,
@@ -92,17 +98,13 @@ impl CosmicPanelContainerConfig {
         }
     }
 
-    pub fn configs_for_output(&self, output_name: &str) -> Vec<&CosmicPanelConfig> {
+    pub fn configs_for_output(&self, output: &OutputIdentity) -> Vec<&CosmicPanelConfig> {
         let mut configs: Vec<_> = self
             .config_list
             .iter()
-            .filter(|c| match &c.output {
-                CosmicPanelOuput::All => true,
-                CosmicPanelOuput::Name(n) => n == output_name,
-                _ => false,
-            })
+            .filter(|c| c.output.matches(output))
             .collect();
-        configs.sort_by(|a, b| b.get_priority().cmp(&a.get_priority()));
+        configs.sort_by(|a, b| b.cmp_priority(a));
         configs
     }
 
@@ -110,6 +112,7 @@ impl CosmicPanelContainerConfig {
         Config::new(NAME, VERSION)
     }
 
+    /// write every key of the panel entries to the user layer, overriding the system defaults
     pub fn write_entries(&self) -> Result<(), cosmic_config::Error> {
         let config = Self::cosmic_config()?;
         let entry_names = self
@@ -140,32 +143,54 @@ impl Default for CosmicPanelContainerConfig {
                     size: crate::PanelSize::XS,
                     output: CosmicPanelOuput::All,
                     background: CosmicPanelBackground::ThemeDefault,
-                    plugins_wings: Some((
-                        vec![
-                            "com.system76.CosmicPanelWorkspacesButton".to_string(),
-                            "com.system76.CosmicPanelAppButton".to_string(),
-                        ],
-                        vec![
-                            "com.system76.CosmicAppletInputSources".to_string(),
-                            "com.system76.CosmicAppletStatusArea".to_string(),
-                            "com.system76.CosmicAppletTiling".to_string(),
-                            "com.system76.CosmicAppletAudio".to_string(),
-                            "com.system76.CosmicAppletNetwork".to_string(),
-                            "com.system76.CosmicAppletBattery".to_string(),
-                            "com.system76.CosmicAppletNotifications".to_string(),
-                            "com.system76.CosmicAppletBluetooth".to_string(),
-                            "com.system76.CosmicAppletPower".to_string(),
-                        ],
-                    )),
-                    plugins_center: Some(vec!["com.system76.CosmicAppletTime".to_string()]),
-                    expand_to_edges: true,
+                    zones: vec![
+                        AppletZone::new(
+                            "start",
+                            ZoneAlignment::Start,
+                            vec![
+                                "com.system76.CosmicPanelWorkspacesButton".into(),
+                                "com.system76.CosmicPanelAppButton".into(),
+                            ],
+                        ),
+                        AppletZone::new(
+                            "center",
+                            ZoneAlignment::Center,
+                            vec!["com.system76.CosmicAppletTime".into()],
+                        ),
+                        AppletZone::new(
+                            "end",
+                            ZoneAlignment::End,
+                            vec![
+                                "com.system76.CosmicAppletInputSources".into(),
+                                "com.system76.CosmicAppletStatusArea".into(),
+                                "com.system76.CosmicAppletTiling".into(),
+                                "com.system76.CosmicAppletAudio".into(),
+                                "com.system76.CosmicAppletNetwork".into(),
+                                "com.system76.CosmicAppletBattery".into(),
+                                "com.system76.CosmicAppletNotifications".into(),
+                                "com.system76.CosmicAppletBluetooth".into(),
+                                "com.system76.CosmicAppletPower".into(),
+                            ],
+                        ),
+                    ],
+                    kind: PanelKind::Panel,
                     padding: 0,
                     spacing: 2,
                     border_radius: 0,
                     exclusive_zone: true,
                     autohide: None,
+                    animation: AnimationConfig::default(),
                     margin: 0,
+                    margins: None,
+                    alignment: PanelAlignment::Center,
+                    border: None,
+                    shadow: None,
+                    min_length: None,
+                    max_length: None,
                     opacity: 1.0,
+                    maximized_style: MaximizedStyle::default(),
+                    overrides: Vec::new(),
+                    priority: None,
                 },
                 CosmicPanelConfig {
                     name: "Dock".to_string(),
@@ -177,15 +202,18 @@ impl Default for CosmicPanelContainerConfig {
                     size: crate::PanelSize::L,
                     output: CosmicPanelOuput::All,
                     background: CosmicPanelBackground::ThemeDefault,
-                    plugins_wings: None,
-                    plugins_center: Some(vec![
-                        "com.system76.CosmicPanelLauncherButton".to_string(),
-                        "com.system76.CosmicPanelWorkspacesButton".to_string(),
-                        "com.system76.CosmicPanelAppButton".to_string(),
-                        "com.system76.CosmicAppList".to_string(),
-                        "com.system76.CosmicAppletMinimize".to_string(),
-                    ]),
-                    expand_to_edges: false,
+                    zones: vec![AppletZone::new(
+                        "center",
+                        ZoneAlignment::Center,
+                        vec![
+                            "com.system76.CosmicPanelLauncherButton".into(),
+                            "com.system76.CosmicPanelWorkspacesButton".into(),
+                            "com.system76.CosmicPanelAppButton".into(),
+                            "com.system76.CosmicAppList".into(),
+                            "com.system76.CosmicAppletMinimize".into(),
+                        ],
+                    )],
+                    kind: PanelKind::Dock(DockConfig::default()),
                     padding: 0,
                     spacing: 4,
                     border_radius: 160,
@@ -194,9 +222,23 @@ impl Default for CosmicPanelContainerConfig {
                         wait_time: 500,
                         transition_time: 200,
                         handle_size: 2,
+                        easing: Easing::EaseOutCubic,
                     }),
+                    animation: AnimationConfig {
+                        easing: Easing::EaseOutCubic,
+                        ..Default::default()
+                    },
                     margin: 0,
+                    margins: None,
+                    alignment: PanelAlignment::Center,
+                    border: None,
+                    shadow: None,
+                    min_length: None,
+                    max_length: None,
                     opacity: 1.0,
+                    maximized_style: MaximizedStyle::default(),
+                    overrides: Vec::new(),
+                    priority: None,
                 },
             ],
         }
@@ -0,0 +1,235 @@
+//! Differences between panel configurations, and how they can be applied to a running panel
+
+use crate::CosmicPanelConfig;
+
+/// how a changed field can be applied to a running panel, ordered from least to most disruptive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChangeKind {
+    /// the existing panel is updated in place
+    Live,
+    /// the applets of the panel must be restarted to pick up the change
+    AppletRestart,
+    /// the panel surface must be recreated
+    Recreate,
+}
+
+/// a changed field of a panel config
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfigChange {
+    /// name of the field, as used for its config key
+    pub field: &'static str,
+    pub kind: ChangeKind,
+}
+
+/// all changed fields between two panel configs, see [`CosmicPanelConfig::diff`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigChangeSet {
+    pub changes: Vec<ConfigChange>,
+}
+
+impl ConfigChangeSet {
+    fn check(&mut self, field: &'static str, changed: bool, kind: ChangeKind) {
+        if changed {
+            self.changes.push(ConfigChange { field, kind });
+        }
+    }
+
+    /// whether no field changed
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// the most disruptive kind of change, or None if nothing changed
+    pub fn kind(&self) -> Option<ChangeKind> {
+        self.changes.iter().map(|c| c.kind).max()
+    }
+
+    /// whether every change can be applied to the existing panel
+    pub fn is_live(&self) -> bool {
+        self.kind().map_or(true, |k| k == ChangeKind::Live)
+    }
+
+    /// whether the applets of the panel must be restarted, which recreating the panel also does
+    pub fn needs_applet_restart(&self) -> bool {
+        self.kind() >= Some(ChangeKind::AppletRestart)
+    }
+
+    /// whether the panel surface must be recreated
+    pub fn needs_recreate(&self) -> bool {
+        self.kind() == Some(ChangeKind::Recreate)
+    }
+
+    /// whether the given field changed
+    pub fn contains(&self, field: &str) -> bool {
+        self.changes.iter().any(|c| c.field == field)
+    }
+
+    /// names of the changed fields
+    pub fn fields(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.changes.iter().map(|c| c.field)
+    }
+}
+
+impl CosmicPanelConfig {
+    /// compare the config to a newer version of it, classifying how each changed field can be applied
+    pub fn diff(&self, other: &Self) -> ConfigChangeSet {
+        use ChangeKind::*;
+
+        // every field is listed, so a new field can't be added without classifying it
+        let CosmicPanelConfig {
+            name,
+            anchor,
+            anchor_gap,
+            layer,
+            keyboard_interactivity,
+            size,
+            output,
+            background,
+            zones,
+            kind,
+            padding,
+            spacing,
+            border_radius,
+            exclusive_zone,
+            autohide,
+            animation,
+            margin,
+            margins,
+            alignment,
+            border,
+            shadow,
+            min_length,
+            max_length,
+            opacity,
+            maximized_style,
+            overrides,
+            priority,
+        } = self;
+
+        let mut changes = ConfigChangeSet::default();
+        changes.check("name", *name != other.name, Recreate);
+        // applets are told the anchor, and panels on other edges may need to be stacked differently
+        changes.check("anchor", *anchor != other.anchor, Recreate);
+        changes.check("anchor_gap", *anchor_gap != other.anchor_gap, Live);
+        changes.check("layer", *layer != other.layer, Recreate);
+        changes.check(
+            "keyboard_interactivity",
+            *keyboard_interactivity != other.keyboard_interactivity,
+            Recreate,
+        );
+        changes.check("size", *size != other.size, AppletRestart);
+        changes.check("output", *output != other.output, Recreate);
+        changes.check("background", *background != other.background, AppletRestart);
+        // only added and removed applets are started and stopped
+        changes.check("zones", *zones != other.zones, Live);
+        changes.check("kind", *kind != other.kind, Live);
+        changes.check("padding", *padding != other.padding, Live);
+        changes.check("spacing", *spacing != other.spacing, Live);
+        changes.check("border_radius", *border_radius != other.border_radius, Live);
+        changes.check(
+            "exclusive_zone",
+            *exclusive_zone != other.exclusive_zone,
+            Live,
+        );
+        changes.check("autohide", *autohide != other.autohide, Live);
+        changes.check("animation", *animation != other.animation, Live);
+        changes.check("margin", *margin != other.margin, Live);
+        changes.check("margins", *margins != other.margins, Live);
+        changes.check("alignment", *alignment != other.alignment, Live);
+        changes.check("border", *border != other.border, Live);
+        changes.check("shadow", *shadow != other.shadow, Live);
+        changes.check("min_length", *min_length != other.min_length, Live);
+        changes.check("max_length", *max_length != other.max_length, Live);
+        changes.check("opacity", (opacity - other.opacity).abs() >= 0.01, Live);
+        changes.check(
+            "maximized_style",
+            *maximized_style != other.maximized_style,
+            Live,
+        );
+        // overrides may change the size or anchor on some outputs
+        changes.check("overrides", *overrides != other.overrides, Recreate);
+        // the stacking order relative to other panels is checked by the panel container
+        changes.check("priority", *priority != other.priority, Live);
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AppletZone, CosmicPanelBackground, CosmicPanelOuput, DockConfig, PanelAnchor, PanelKind,
+        PanelSize, ZoneAlignment,
+    };
+
+    fn diff(change: impl FnOnce(&mut CosmicPanelConfig)) -> ConfigChangeSet {
+        let old = CosmicPanelConfig::default();
+        let mut new = old.clone();
+        change(&mut new);
+        old.diff(&new)
+    }
+
+    #[test]
+    fn unchanged() {
+        let changes = diff(|_| {});
+        assert!(changes.is_empty());
+        assert_eq!(changes.kind(), None);
+        assert!(changes.is_live());
+        assert_eq!(CosmicPanelConfig::default(), CosmicPanelConfig::default());
+    }
+
+    #[test]
+    fn live() {
+        let changes = diff(|c| {
+            c.padding += 2;
+            c.kind = PanelKind::Dock(DockConfig::default());
+            c.opacity = 0.5;
+        });
+        assert_eq!(changes.kind(), Some(ChangeKind::Live));
+        assert!(changes.is_live());
+        assert!(!changes.needs_applet_restart());
+        assert_eq!(
+            changes.fields().collect::<Vec<_>>(),
+            ["kind", "padding", "opacity"]
+        );
+
+        let changes =
+            diff(|c| c.zones = vec![AppletZone::new("start", ZoneAlignment::Start, Vec::new())]);
+        assert_eq!(changes.kind(), Some(ChangeKind::Live));
+    }
+
+    #[test]
+    fn opacity_tolerance() {
+        assert!(diff(|c| c.opacity += 0.001).is_empty());
+    }
+
+    #[test]
+    fn applet_restart() {
+        let changes = diff(|c| {
+            c.size = PanelSize::XL;
+            c.spacing += 1;
+        });
+        assert_eq!(changes.kind(), Some(ChangeKind::AppletRestart));
+        assert!(changes.needs_applet_restart());
+        assert!(!changes.needs_recreate());
+        assert!(changes.contains("size"));
+
+        let changes = diff(|c| c.background = CosmicPanelBackground::Light);
+        assert_eq!(changes.kind(), Some(ChangeKind::AppletRestart));
+    }
+
+    #[test]
+    fn recreate() {
+        let changes = diff(|c| {
+            c.anchor = PanelAnchor::Bottom;
+            c.size = PanelSize::XL;
+        });
+        assert_eq!(changes.kind(), Some(ChangeKind::Recreate));
+        assert!(changes.needs_recreate());
+        assert!(changes.needs_applet_restart());
+
+        let changes = diff(|c| c.output = CosmicPanelOuput::Name("DP-1".to_string()));
+        assert!(changes.needs_recreate());
+        assert_eq!(changes.fields().collect::<Vec<_>>(), ["output"]);
+    }
+}
@@ -0,0 +1,194 @@
+//! Layered panel configuration
+//!
+//! The system layer holds the defaults installed from `default_schema`, and the user layer holds
+//! the keys which the user changed. Each key is read from the user layer if it is set there, and
+//! from the system layer otherwise, so later changes to the system defaults still apply to every
+//! key which the user did not override.
+
+use std::{fs, io, path::PathBuf};
+
+use cosmic_config::{Config, ConfigGet, CosmicConfigEntry};
+use tracing::{error, warn};
+
+use crate::{CosmicPanelConfig, CosmicPanelContainerConfig, NAME, VERSION};
+
+/// a layer of the panel configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConfigLayer {
+    /// defaults installed with the panel
+    System,
+    /// changes made by the user
+    User,
+}
+
+fn version_path(name: &str) -> PathBuf {
+    PathBuf::from(name).join(format!("v{VERSION}"))
+}
+
+fn entry_name(entry: &str) -> String {
+    format!("{NAME}.{entry}")
+}
+
+/// directory of the system layer of a config, if it is installed
+pub fn system_dir(name: &str) -> Option<PathBuf> {
+    xdg::BaseDirectories::with_prefix("cosmic")
+        .ok()?
+        .find_data_file(version_path(name))
+}
+
+/// directory of the user layer of a config, which may not exist yet
+pub fn user_dir(name: &str) -> anyhow::Result<PathBuf> {
+    Ok(crate::config_home()?.join(version_path(name)))
+}
+
+/// keys of a config which are set in the user layer
+pub fn overridden_keys(name: &str) -> anyhow::Result<Vec<String>> {
+    let dir = user_dir(name)?;
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut keys = Vec::new();
+    for e in entries {
+        let path = e?.path();
+        // editors and cosmic-config write keys atomically through hidden temporary files
+        match path.file_name().and_then(|n| n.to_str()) {
+            Some(key) if path.is_file() && !key.starts_with('.') => keys.push(key.to_string()),
+            _ => {}
+        }
+    }
+    keys.sort();
+    Ok(keys)
+}
+
+/// remove a key from the user layer, so it is read from the system layer again
+pub fn reset_key(name: &str, key: &str) -> anyhow::Result<()> {
+    match fs::remove_file(user_dir(name)?.join(key)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// the layer which a key of a config is read from
+pub fn layer_of(name: &str, key: &str) -> ConfigLayer {
+    if user_dir(name).is_ok_and(|dir| dir.join(key).is_file()) {
+        ConfigLayer::User
+    } else {
+        ConfigLayer::System
+    }
+}
+
+impl CosmicPanelContainerConfig {
+    /// load the names of the panel entries, from the layer which sets them
+    pub fn load_entry_names() -> Result<Vec<String>, cosmic_config::Error> {
+        let config = match layer_of(NAME, "entries") {
+            ConfigLayer::User => Config::new(NAME, VERSION)?,
+            ConfigLayer::System => Config::system(NAME, VERSION)?,
+        };
+        config.get::<Vec<String>>("entries")
+    }
+
+    /// load every panel entry with the user layer applied on top of the system layer
+    pub fn load_layered() -> Result<Self, (Vec<cosmic_config::Error>, Self)> {
+        let entry_names = match Self::load_entry_names() {
+            Ok(names) => names,
+            Err(e) => {
+                warn!("Falling back to default panel configuration");
+                return Err((vec![e], Self::default()));
+            }
+        };
+        let mut config_list = Vec::new();
+        let mut entry_errors = Vec::new();
+        for name in entry_names {
+            match CosmicPanelConfig::load_layered(&name) {
+                Ok(entry) => config_list.push(entry),
+                Err((mut errors, entry)) => {
+                    config_list.push(entry);
+                    entry_errors.append(&mut errors);
+                }
+            }
+        }
+        if entry_errors.is_empty() {
+            Ok(Self { config_list })
+        } else {
+            Err((entry_errors, Self { config_list }))
+        }
+    }
+
+    /// keys of the container config which are set in the user layer
+    pub fn overridden_keys() -> anyhow::Result<Vec<String>> {
+        overridden_keys(NAME)
+    }
+
+    /// reset a key of the container config to its system default
+    pub fn reset_key(key: &str) -> anyhow::Result<()> {
+        reset_key(NAME, key)
+    }
+}
+
+impl CosmicPanelConfig {
+    pub fn system_config(name: &str) -> Result<Config, cosmic_config::Error> {
+        Config::system(&entry_name(name), VERSION)
+    }
+
+    /// load a panel entry with the user layer applied on top of the system layer
+    /// entries which are not installed with the panel start from the default config
+    pub fn load_layered(name: &str) -> Result<Self, (Vec<cosmic_config::Error>, Self)> {
+        let mut errors = Vec::new();
+        let default = || Self {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let mut entry = if system_dir(&entry_name(name)).is_some() {
+            match Self::system_config(name).map(|config| Self::get_entry(&config)) {
+                Ok(Ok(entry)) => entry,
+                Ok(Err((mut e, entry))) => {
+                    errors.append(&mut e);
+                    entry
+                }
+                Err(e) => {
+                    errors.push(e);
+                    default()
+                }
+            }
+        } else {
+            default()
+        };
+
+        let keys = Self::overridden_keys(name).unwrap_or_else(|e| {
+            error!("Failed to list the overridden keys of {name}: {e}");
+            Vec::new()
+        });
+        if !keys.is_empty() {
+            match Self::cosmic_config(name) {
+                Ok(config) => {
+                    let (mut e, _) = entry.update_keys(&config, &keys);
+                    errors.append(&mut e);
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(entry)
+        } else {
+            Err((errors, entry))
+        }
+    }
+
+    /// keys of a panel entry which are set in the user layer
+    pub fn overridden_keys(name: &str) -> anyhow::Result<Vec<String>> {
+        overridden_keys(&entry_name(name))
+    }
+
+    /// reset a key of a panel entry to its system default
+    pub fn reset_key(name: &str, key: &str) -> anyhow::Result<()> {
+        reset_key(&entry_name(name), key)
+    }
+
+    /// the layer which a key of a panel entry is read from
+    pub fn layer_of(name: &str, key: &str) -> ConfigLayer {
+        layer_of(&entry_name(name), key)
+    }
+}
@@ -1,8 +1,26 @@
 //! Config for cosmic-panel
+mod applet_config;
 #[cfg(feature = "wayland-rs")]
 mod container_config;
+#[cfg(feature = "wayland-rs")]
+mod diff;
+#[cfg(feature = "wayland-rs")]
+mod layers;
+#[cfg(feature = "wayland-rs")]
+mod migration;
 mod panel_config;
+#[cfg(feature = "wayland-rs")]
+mod validation;
 
+pub use applet_config::*;
 #[cfg(feature = "wayland-rs")]
 pub use container_config::*;
+#[cfg(feature = "wayland-rs")]
+pub use diff::*;
+#[cfg(feature = "wayland-rs")]
+pub use layers::*;
+#[cfg(feature = "wayland-rs")]
+pub use migration::*;
 pub use panel_config::*;
+#[cfg(feature = "wayland-rs")]
+pub use validation::*;
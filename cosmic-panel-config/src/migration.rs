@@ -0,0 +1,468 @@
+//! Migration of panel configurations stored for older versions of the config schema
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail};
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::info;
+
+use crate::{AppletEntry, AppletZone, DockConfig, PanelKind, ZoneAlignment, NAME, VERSION};
+
+/// raw keys of a config directory, mapping each key to its RON encoded value
+pub type ConfigKeys = BTreeMap<String, String>;
+
+/// all panel config data stored for a single version
+#[derive(Debug, Default, Clone)]
+pub struct VersionedConfig {
+    /// keys of the container config, e.g. `entries`
+    pub container: ConfigKeys,
+    /// keys of each panel entry, by entry name
+    pub entries: BTreeMap<String, ConfigKeys>,
+}
+
+/// a transform of the config data from one version to the next
+pub struct Migration {
+    /// version which is migrated from, the result is stored as version `from + 1`
+    pub from: u64,
+    /// transform the data in place
+    pub migrate: fn(&mut VersionedConfig) -> anyhow::Result<()>,
+}
+
+/// chain of migrations, one for each version before [`VERSION`]
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 1,
+        migrate: v1_panel_kind,
+    },
+    Migration {
+        from: 2,
+        migrate: v2_applet_zones,
+    },
+];
+
+/// v1 -> v2: replace `expand_to_edges` with an explicit `kind`
+/// entries which did not set `expand_to_edges` keep reading `kind` from the system defaults
+fn v1_panel_kind(config: &mut VersionedConfig) -> anyhow::Result<()> {
+    for keys in config.entries.values_mut() {
+        let Some(expand_to_edges) = get_key::<bool>(keys, "expand_to_edges")? else {
+            continue;
+        };
+        keys.remove("expand_to_edges");
+        let kind = if expand_to_edges {
+            PanelKind::Panel
+        } else {
+            PanelKind::Dock(DockConfig::default())
+        };
+        set_key(keys, "kind", &kind)?;
+    }
+    Ok(())
+}
+
+/// `plugins_wings` and `plugins_center` of the stock entries in the v1 and v2 system defaults
+const V2_DEFAULT_APPLETS: &[(&str, &str, &str)] = &[
+    (
+        "Panel",
+        r#"Some((["com.system76.CosmicPanelWorkspacesButton", "com.system76.CosmicPanelAppButton"],["com.system76.CosmicAppletInputSources", "com.system76.CosmicAppletStatusArea", "com.system76.CosmicAppletTiling", "com.system76.CosmicAppletAudio", "com.system76.CosmicAppletNetwork", "com.system76.CosmicAppletBattery", "com.system76.CosmicAppletNotifications", "com.system76.CosmicAppletBluetooth", "com.system76.CosmicAppletPower"]))"#,
+        r#"Some(["com.system76.CosmicAppletTime"])"#,
+    ),
+    (
+        "Dock",
+        "None",
+        r#"Some(["com.system76.CosmicPanelLauncherButton", "com.system76.CosmicPanelWorkspacesButton", "com.system76.CosmicPanelAppButton", "com.system76.CosmicAppList", "com.system76.CosmicAppletMinimize"])"#,
+    ),
+];
+
+/// v2 -> v3: split `plugins_wings` and `plugins_center` into named zones
+/// entries which set neither key keep reading `zones` from the system defaults
+fn v2_applet_zones(config: &mut VersionedConfig) -> anyhow::Result<()> {
+    for (entry, keys) in config.entries.iter_mut() {
+        if !keys.contains_key("plugins_wings") && !keys.contains_key("plugins_center") {
+            continue;
+        }
+        // `zones` replaces both keys, so the side which was not changed keeps its defaults
+        if let Some((_, wings, center)) = V2_DEFAULT_APPLETS.iter().find(|(name, ..)| name == entry)
+        {
+            keys.entry("plugins_wings".to_string())
+                .or_insert_with(|| wings.to_string());
+            keys.entry("plugins_center".to_string())
+                .or_insert_with(|| center.to_string());
+        }
+        let (start, end) =
+            get_key::<Option<(Vec<AppletEntry>, Vec<AppletEntry>)>>(keys, "plugins_wings")?
+                .flatten()
+                .unwrap_or_default();
+        let center = get_key::<Option<Vec<AppletEntry>>>(keys, "plugins_center")?
+            .flatten()
+            .unwrap_or_default();
+        keys.remove("plugins_wings");
+        keys.remove("plugins_center");
+
+        let zones: Vec<_> = [
+            ("start", ZoneAlignment::Start, start),
+            ("center", ZoneAlignment::Center, center),
+            ("end", ZoneAlignment::End, end),
+        ]
+        .into_iter()
+        .filter(|(_, _, applets)| !applets.is_empty())
+        .map(|(name, alignment, applets)| AppletZone::new(name, alignment, applets))
+        .collect();
+        set_key(keys, "zones", &zones)?;
+    }
+    Ok(())
+}
+
+/// summary of a completed migration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// oldest version which was migrated from
+    pub from: u64,
+    pub to: u64,
+    /// names of the migrated panel entries
+    pub entries: Vec<String>,
+    /// directories containing a copy of each migrated config before migrating
+    pub backups: Vec<PathBuf>,
+}
+
+/// read a key from the raw config data
+pub fn get_key<T: DeserializeOwned>(keys: &ConfigKeys, key: &str) -> anyhow::Result<Option<T>> {
+    keys.get(key)
+        .map(|v| ron::from_str(v).map_err(|e| anyhow!("Failed to parse {key}: {e}")))
+        .transpose()
+}
+
+/// write a key to the raw config data
+pub fn set_key<T: Serialize>(keys: &mut ConfigKeys, key: &str, value: &T) -> anyhow::Result<()> {
+    keys.insert(key.to_string(), ron::ser::to_string(value)?);
+    Ok(())
+}
+
+/// directory containing the user's cosmic configs
+pub fn config_home() -> anyhow::Result<PathBuf> {
+    Ok(xdg::BaseDirectories::with_prefix("cosmic")?.get_config_home())
+}
+
+fn version_dir(config_home: &Path, name: &str, version: u64) -> PathBuf {
+    config_home.join(name).join(format!("v{version}"))
+}
+
+fn entry_name(entry: &str) -> String {
+    format!("{NAME}.{entry}")
+}
+
+/// names of the panel entries with a config directory, whatever version it contains
+fn stored_entries(config_home: &Path) -> io::Result<Vec<String>> {
+    let dirs = match fs::read_dir(config_home) {
+        Ok(dirs) => dirs,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut entries = Vec::new();
+    for e in dirs {
+        let name = e?.file_name();
+        if let Some(entry) = name
+            .to_str()
+            .and_then(|n| n.strip_prefix(NAME))
+            .and_then(|n| n.strip_prefix('.'))
+            .filter(|n| !n.is_empty())
+        {
+            entries.push(entry.to_string());
+        }
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+/// newest older version with data for a config, if the current version has none yet
+fn pending_version(config_home: &Path, name: &str) -> Option<u64> {
+    if has_keys(&version_dir(config_home, name, VERSION)) {
+        return None;
+    }
+    (1..VERSION)
+        .rev()
+        .find(|v| has_keys(&version_dir(config_home, name, *v)))
+}
+
+fn has_keys(dir: &Path) -> bool {
+    fs::read_dir(dir)
+        .map(|mut d| d.any(|e| e.is_ok_and(|e| e.path().is_file())))
+        .unwrap_or_default()
+}
+
+fn read_keys(dir: &Path) -> io::Result<ConfigKeys> {
+    let mut keys = ConfigKeys::new();
+    for e in fs::read_dir(dir)? {
+        let path = e?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let Some(key) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        keys.insert(key.to_string(), fs::read_to_string(&path)?);
+    }
+    Ok(keys)
+}
+
+fn write_keys(dir: &Path, keys: &ConfigKeys) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for (key, value) in keys {
+        fs::write(dir.join(key), value)?;
+    }
+    Ok(())
+}
+
+fn copy_keys(from: &Path, to: &Path) -> io::Result<()> {
+    write_keys(to, &read_keys(from)?)
+}
+
+impl VersionedConfig {
+    /// read the config data stored for the given version
+    /// only configs with keys in that version are read, the others are left empty or out
+    pub fn read(config_home: &Path, version: u64) -> anyhow::Result<Self> {
+        let dir = version_dir(config_home, NAME, version);
+        let container = if dir.is_dir() {
+            read_keys(&dir)?
+        } else {
+            ConfigKeys::new()
+        };
+        let mut entries = BTreeMap::new();
+        for entry in stored_entries(config_home)? {
+            let dir = version_dir(config_home, &entry_name(&entry), version);
+            if has_keys(&dir) {
+                entries.insert(entry, read_keys(&dir)?);
+            }
+        }
+        Ok(Self { container, entries })
+    }
+
+    /// write the config data for the given version, an empty container config is not written
+    /// the container config is written last, so an interrupted write is migrated again
+    pub fn write(&self, config_home: &Path, version: u64) -> anyhow::Result<()> {
+        for (entry, keys) in &self.entries {
+            write_keys(&version_dir(config_home, &entry_name(entry), version), keys)?;
+        }
+        if !self.container.is_empty() {
+            write_keys(&version_dir(config_home, NAME, version), &self.container)?;
+        }
+        Ok(())
+    }
+
+    /// apply the chain of migrations from `from` up to `to`
+    pub fn migrate(&mut self, from: u64, to: u64) -> anyhow::Result<()> {
+        for version in from..to {
+            let Some(migration) = MIGRATIONS.iter().find(|m| m.from == version) else {
+                bail!("No migration from version {version}");
+            };
+            (migration.migrate)(self)?;
+        }
+        Ok(())
+    }
+}
+
+/// migrate each of the user's configs from its newest older version, if the current version has
+/// no data for it yet
+/// the older version is left in place, and an additional copy is kept in a `v<n>.bak` directory
+pub fn migrate() -> anyhow::Result<Option<MigrationReport>> {
+    migrate_in(&config_home()?)
+}
+
+/// migrate the configs stored in the given config directory
+/// the container config and each entry are migrated on their own, so entries which were changed
+/// without changing the container config, or which are not listed in it, are migrated too
+pub fn migrate_in(config_home: &Path) -> anyhow::Result<Option<MigrationReport>> {
+    let mut report: Option<MigrationReport> = None;
+    let names: Vec<_> = std::iter::once(NAME.to_string())
+        .chain(stored_entries(config_home)?.iter().map(|e| entry_name(e)))
+        .collect();
+
+    for from in 1..VERSION {
+        let pending: Vec<_> = names
+            .iter()
+            .filter(|name| pending_version(config_home, name) == Some(from))
+            .collect();
+        if pending.is_empty() {
+            continue;
+        }
+        info!("Migrating panel config from version {from} to {VERSION}");
+
+        let mut config = VersionedConfig::read(config_home, from)?;
+        if !pending.iter().any(|name| *name == NAME) {
+            config.container.clear();
+        }
+        config
+            .entries
+            .retain(|entry, _| pending.contains(&&entry_name(entry)));
+
+        let report = report.get_or_insert_with(|| MigrationReport {
+            from,
+            to: VERSION,
+            entries: Vec::new(),
+            backups: Vec::new(),
+        });
+        for name in pending {
+            let backup = config_home.join(name).join(format!("v{from}.bak"));
+            copy_keys(&version_dir(config_home, name, from), &backup)?;
+            report.backups.push(backup);
+        }
+
+        config.migrate(from, VERSION)?;
+        config.write(config_home, VERSION)?;
+        report.entries.extend(config.entries.into_keys());
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(config_home: &Path, name: &str, version: u64, keys: &[(&str, &str)]) {
+        let keys = keys
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        write_keys(&version_dir(config_home, name, version), &keys).unwrap();
+    }
+
+    fn read(config_home: &Path, name: &str, version: u64) -> ConfigKeys {
+        read_keys(&version_dir(config_home, name, version)).unwrap()
+    }
+
+    fn zones(keys: &ConfigKeys) -> Vec<AppletZone> {
+        get_key(keys, "zones").unwrap().unwrap()
+    }
+
+    #[test]
+    fn current_version() {
+        let home = tempfile::tempdir().unwrap();
+        write(home.path(), NAME, VERSION, &[("entries", r#"["Panel"]"#)]);
+        write(home.path(), &entry_name("Panel"), 1, &[("size", "M")]);
+        write(home.path(), &entry_name("Panel"), VERSION, &[("size", "L")]);
+        assert_eq!(migrate_in(home.path()).unwrap(), None);
+        assert_eq!(
+            read(home.path(), &entry_name("Panel"), VERSION).get("size"),
+            Some(&"L".to_string())
+        );
+    }
+
+    #[test]
+    fn v1_customized_dock() {
+        let home = tempfile::tempdir().unwrap();
+        write(home.path(), NAME, 1, &[("entries", r#"["Panel","Dock"]"#)]);
+        write(
+            home.path(),
+            &entry_name("Dock"),
+            1,
+            &[("size", "L"), ("plugins_center", r#"Some(["A", "B"])"#)],
+        );
+
+        let report = migrate_in(home.path()).unwrap().unwrap();
+        assert_eq!(report.from, 1);
+        assert_eq!(report.to, VERSION);
+        assert_eq!(report.entries, vec!["Dock".to_string()]);
+
+        let dock = read(home.path(), &entry_name("Dock"), VERSION);
+        assert_eq!(dock.get("size").map(String::as_str), Some("L"));
+        // the kind and the other applets still come from the system defaults
+        assert!(!dock.contains_key("kind"));
+        assert!(!dock.contains_key("plugins_center"));
+        assert_eq!(
+            zones(&dock),
+            vec![AppletZone::new(
+                "center",
+                ZoneAlignment::Center,
+                vec!["A".into(), "B".into()]
+            )]
+        );
+        assert_eq!(
+            read(home.path(), NAME, VERSION).get("entries"),
+            Some(&r#"["Panel","Dock"]"#.to_string())
+        );
+    }
+
+    #[test]
+    fn v1_entry_without_directory() {
+        let home = tempfile::tempdir().unwrap();
+        write(home.path(), NAME, 1, &[("entries", r#"["Panel","Dock"]"#)]);
+
+        let report = migrate_in(home.path()).unwrap().unwrap();
+        assert!(report.entries.is_empty());
+        for entry in ["Panel", "Dock"] {
+            assert!(!version_dir(home.path(), &entry_name(entry), VERSION).exists());
+        }
+    }
+
+    #[test]
+    fn v1_entry_without_container() {
+        let home = tempfile::tempdir().unwrap();
+        write(
+            home.path(),
+            &entry_name("Panel"),
+            1,
+            &[
+                ("expand_to_edges", "false"),
+                (
+                    "plugins_wings",
+                    r#"Some((["A"], ["com.system76.CosmicAppletPower"]))"#,
+                ),
+            ],
+        );
+
+        let report = migrate_in(home.path()).unwrap().unwrap();
+        assert_eq!(report.entries, vec!["Panel".to_string()]);
+        assert!(!version_dir(home.path(), NAME, VERSION).exists());
+
+        let panel = read(home.path(), &entry_name("Panel"), VERSION);
+        assert!(!panel.contains_key("expand_to_edges"));
+        assert_eq!(
+            get_key::<PanelKind>(&panel, "kind").unwrap(),
+            Some(PanelKind::Dock(DockConfig::default()))
+        );
+        let zones = zones(&panel);
+        assert_eq!(
+            zones.iter().map(|z| z.name.as_str()).collect::<Vec<_>>(),
+            vec!["start", "center", "end"]
+        );
+        // the center keeps the applets of the v1 defaults
+        assert_eq!(
+            zones[1].applets,
+            vec![AppletEntry::from("com.system76.CosmicAppletTime")]
+        );
+    }
+
+    #[test]
+    fn v1_backup() {
+        let home = tempfile::tempdir().unwrap();
+        write(home.path(), NAME, 1, &[("entries", r#"["Dock"]"#)]);
+        write(
+            home.path(),
+            &entry_name("Dock"),
+            1,
+            &[("expand_to_edges", "true")],
+        );
+
+        let report = migrate_in(home.path()).unwrap().unwrap();
+        assert_eq!(
+            report.backups,
+            vec![
+                home.path().join(NAME).join("v1.bak"),
+                home.path().join(entry_name("Dock")).join("v1.bak"),
+            ]
+        );
+        assert_eq!(
+            read_keys(&report.backups[1]).unwrap(),
+            read(home.path(), &entry_name("Dock"), 1)
+        );
+        assert_eq!(
+            read_keys(&report.backups[0]).unwrap(),
+            read(home.path(), NAME, 1)
+        );
+        // the old version is left in place
+        assert!(read(home.path(), &entry_name("Dock"), 1).contains_key("expand_to_edges"));
+    }
+}
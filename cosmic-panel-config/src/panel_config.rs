@@ -1,17 +1,25 @@
 //! Config for cosmic-panel
 
-use std::{fmt::Display, ops::Range, str::FromStr, time::Duration};
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display},
+    ops::Range,
+    str::FromStr,
+    time::Duration,
+};
 
 use anyhow::bail;
 use cosmic_config::{cosmic_config_derive::CosmicConfigEntry, Config, CosmicConfigEntry};
+#[cfg(feature = "wayland-rs")]
+use sctk::output::OutputInfo;
 use sctk::shell::wlr_layer::Anchor;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 #[cfg(feature = "wayland-rs")]
 use wayland_protocols_wlr::layer_shell::v1::client::{zwlr_layer_shell_v1, zwlr_layer_surface_v1};
 #[cfg(feature = "wayland-rs")]
 use xdg_shell_wrapper_config::{KeyboardInteractivity, Layer, WrapperConfig, WrapperOutput};
 
-use crate::{NAME, VERSION};
+use crate::{AppletEntry, AppletZone, NAME, VERSION};
 
 /// Edge to which the panel is anchored
 #[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
@@ -120,8 +128,99 @@ impl Into<Anchor> for PanelAnchor {
     }
 }
 
+/// margins on each side of the panel
+/// the side opposite to the anchor is ignored
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct PanelMargins {
+    pub top: u16,
+    pub right: u16,
+    pub bottom: u16,
+    pub left: u16,
+}
+
+impl PanelMargins {
+    /// the same margin on every side
+    pub fn uniform(margin: u16) -> Self {
+        Self {
+            top: margin,
+            right: margin,
+            bottom: margin,
+            left: margin,
+        }
+    }
+
+    /// margin on the edge which the panel is anchored to
+    pub fn edge(&self, anchor: PanelAnchor) -> u16 {
+        match anchor {
+            PanelAnchor::Left => self.left,
+            PanelAnchor::Right => self.right,
+            PanelAnchor::Top => self.top,
+            PanelAnchor::Bottom => self.bottom,
+        }
+    }
+
+    /// margins at the start and end of the edge which the panel is anchored to
+    pub fn lengthwise(&self, anchor: PanelAnchor) -> (u16, u16) {
+        match anchor {
+            PanelAnchor::Left | PanelAnchor::Right => (self.top, self.bottom),
+            PanelAnchor::Top | PanelAnchor::Bottom => (self.left, self.right),
+        }
+    }
+}
+
+/// placement of a panel which does not span the whole length of its edge
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(deny_unknown_fields)]
+pub enum PanelAlignment {
+    /// left or top
+    Start,
+    #[default]
+    Center,
+    /// right or bottom
+    End,
+    /// offset in pixels from the start, or from the end if negative
+    Offset(i32),
+}
+
+impl PanelAlignment {
+    /// position of something of the given length within the available length
+    pub fn position(&self, available: i32, length: i32) -> i32 {
+        let free = (available - length).max(0);
+        match self {
+            PanelAlignment::Start => 0,
+            PanelAlignment::Center => free / 2,
+            PanelAlignment::End => free,
+            PanelAlignment::Offset(offset) if *offset < 0 => (free + offset).max(0),
+            PanelAlignment::Offset(offset) => (*offset).min(free),
+        }
+    }
+}
+
+/// length of the panel along its edge
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub enum PanelLength {
+    /// logical pixels
+    Pixels(u32),
+    /// percentage of the length of the output edge
+    Percent(f32),
+}
+
+impl PanelLength {
+    /// get the length in logical pixels
+    pub fn resolve(&self, output_length: u32) -> u32 {
+        match self {
+            PanelLength::Pixels(length) => *length,
+            PanelLength::Percent(percent) => {
+                (output_length as f32 * percent.clamp(0., 100.) / 100.).round() as u32
+            }
+        }
+    }
+}
+
 /// Configurable size for the cosmic panel
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Hash)]
 #[serde(deny_unknown_fields)]
 pub enum PanelSize {
     /// XS
@@ -134,6 +233,15 @@ pub enum PanelSize {
     L,
     /// XL
     XL,
+    /// pixel exact size
+    Custom {
+        /// size of non-symbolic applet icons
+        icon: u32,
+        /// padding around non-symbolic applet icons
+        padding: u16,
+        /// maximum thickness of the panel bar, excluding the anchor gap
+        max_thickness: u32,
+    },
 }
 
 impl PanelSize {
@@ -146,6 +254,7 @@ impl PanelSize {
                 PanelSize::M => 28,
                 PanelSize::L => 32,
                 PanelSize::XL => 48,
+                PanelSize::Custom { icon, .. } => Self::custom_symbolic_icon_size(*icon),
             }
         } else {
             match self {
@@ -154,10 +263,17 @@ impl PanelSize {
                 PanelSize::M => 40,
                 PanelSize::L => 48,
                 PanelSize::XL => 56,
+                PanelSize::Custom { icon, .. } => *icon,
             }
         }
     }
 
+    /// symbolic icons are about 2/3 of the size of other icons, rounded down to an even size
+    fn custom_symbolic_icon_size(icon: u32) -> u32 {
+        let symbolic = (icon as u64 * 2 / 3) as u32;
+        symbolic - symbolic % 2
+    }
+
     pub fn get_applet_padding(&self, is_symbolic: bool) -> u16 {
         if is_symbolic {
             match self {
@@ -166,6 +282,11 @@ impl PanelSize {
                 PanelSize::M => 14,
                 PanelSize::L => 16,
                 PanelSize::XL => 16,
+                // symbolic applets take up the same space as other applets
+                PanelSize::Custom { icon, padding, .. } => {
+                    let diff = icon - Self::custom_symbolic_icon_size(*icon);
+                    padding.saturating_add(u16::try_from(diff / 2).unwrap_or(u16::MAX))
+                }
             }
         } else {
             match self {
@@ -174,9 +295,23 @@ impl PanelSize {
                 PanelSize::M => 8,
                 PanelSize::L => 8,
                 PanelSize::XL => 12,
+                PanelSize::Custom { padding, .. } => *padding,
             }
         }
     }
+
+    /// get the maximum thickness of the panel bar, excluding the anchor gap
+    /// may be below [`MIN_THICKNESS`] for custom sizes, which are rejected by validation
+    pub fn get_max_thickness(&self) -> u32 {
+        match self {
+            PanelSize::XS => 60,
+            PanelSize::S => 80,
+            PanelSize::M => 100,
+            PanelSize::L => 120,
+            PanelSize::XL => 140,
+            PanelSize::Custom { max_thickness, .. } => *max_thickness,
+        }
+    }
 }
 
 impl Display for PanelSize {
@@ -187,6 +322,14 @@ impl Display for PanelSize {
             PanelSize::M => write!(f, "M"),
             PanelSize::L => write!(f, "L"),
             PanelSize::XL => write!(f, "XL"),
+            PanelSize::Custom {
+                icon,
+                padding,
+                max_thickness,
+            } => write!(
+                f,
+                "Custom(icon: {icon}, padding: {padding}, max_thickness: {max_thickness})"
+            ),
         }
     }
 }
@@ -201,6 +344,9 @@ impl FromStr for PanelSize {
             "M" => Ok(Self::M),
             "L" => Ok(Self::L),
             "XL" => Ok(Self::XL),
+            s if s.starts_with("Custom(") => {
+                ron::from_str(s).map_err(|e| anyhow::anyhow!("Not a valid PanelSize: {e}"))
+            }
             _ => Err(anyhow::anyhow!("Not a valid PanelSize")),
         }
     }
@@ -216,13 +362,204 @@ pub enum CosmicPanelBackground {
     Dark,
     /// theme default light
     Light,
-    /// RGBA
-    Color([f32; 3]),
+    /// RGB or RGBA color, or a CSS color string
+    Color(PanelColor),
+    /// linear gradient along `angle`, in degrees clockwise from the bottom to the top of the panel
+    /// at most [`MAX_GRADIENT_STOPS`] stops are rendered
+    LinearGradient {
+        stops: Vec<GradientStop>,
+        angle: f32,
+    },
+    /// color of the cosmic theme, which follows changes to the theme
+    Theme(ThemeColor),
+}
+
+impl CosmicPanelBackground {
+    /// get the color of the background, if it is a single custom color
+    pub fn color(&self) -> Option<[f32; 4]> {
+        match self {
+            CosmicPanelBackground::Color(c) => Some(c.to_rgba()),
+            _ => None,
+        }
+    }
+
+    /// whether the background is derived from the cosmic theme
+    pub fn follows_theme(&self) -> bool {
+        !matches!(
+            self,
+            CosmicPanelBackground::Color(_) | CosmicPanelBackground::LinearGradient { .. }
+        )
+    }
+}
+
+/// a color of the cosmic theme
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ThemeToken {
+    /// the window background
+    Background,
+    /// the primary container, drawn on top of the background
+    PrimaryContainer,
+    /// the secondary container, drawn on top of the primary container
+    SecondaryContainer,
+    /// the accent color chosen by the user
+    Accent,
+}
+
+/// a theme color, written as `(token: Accent, alpha: Some(0.8), mix: Some((Background, 0.5)))`
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ThemeColor {
+    pub token: ThemeToken,
+    /// replaces the alpha of the theme color, between 0.0 and 1.0
+    #[serde(default)]
+    pub alpha: Option<f32>,
+    /// mixes in a second theme color, with a factor between 0.0 (none of it) and 1.0 (only it)
+    #[serde(default)]
+    pub mix: Option<(ThemeToken, f32)>,
+}
+
+impl ThemeColor {
+    pub fn new(token: ThemeToken) -> Self {
+        Self {
+            token,
+            alpha: None,
+            mix: None,
+        }
+    }
+
+    /// get the color as RGBA components, looking up the theme tokens with `lookup`
+    pub fn resolve(&self, lookup: impl Fn(ThemeToken) -> [f32; 4]) -> [f32; 4] {
+        let mut color = lookup(self.token);
+        if let Some((token, factor)) = self.mix {
+            let factor = factor.clamp(0.0, 1.0);
+            let other = lookup(token);
+            for (c, o) in color.iter_mut().zip(other) {
+                *c += (o - *c) * factor;
+            }
+        }
+        if let Some(alpha) = self.alpha {
+            color[3] = alpha.clamp(0.0, 1.0);
+        }
+        color
+    }
+}
+
+/// maximum number of gradient stops which can be rendered
+pub const MAX_GRADIENT_STOPS: usize = 4;
+
+/// smallest thickness of the panel bar, excluding the anchor gap
+pub const MIN_THICKNESS: u32 = 8;
+
+/// a color stop of a gradient background
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct GradientStop {
+    /// position of the stop along the gradient, between 0.0 and 1.0
+    pub offset: f32,
+    pub color: PanelColor,
+}
+
+/// a custom color, written as `(r, g, b)`, `(r, g, b, a)` with components between 0.0 and 1.0,
+/// or as a CSS color string like `"#1e1e1ecc"` or `"rgba(30, 30, 30, 0.8)"`
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum PanelColor {
+    Rgb([f32; 3]),
+    Rgba([f32; 4]),
+    Css(String),
+}
+
+impl PanelColor {
+    /// get the color as RGBA components, clamped between 0.0 and 1.0
+    pub fn to_rgba(&self) -> [f32; 4] {
+        let rgba = match self {
+            PanelColor::Rgb([r, g, b]) => [*r, *g, *b, 1.0],
+            PanelColor::Rgba(c) => *c,
+            // css strings are checked when the config is deserialized
+            PanelColor::Css(s) => csscolorparser::parse(s)
+                .map(|c| [c.r as f32, c.g as f32, c.b as f32, c.a as f32])
+                .unwrap_or([0.0, 0.0, 0.0, 1.0]),
+        };
+        rgba.map(|c| c.clamp(0.0, 1.0))
+    }
+}
+
+// untagged enums can't be deserialized from RON, and css strings should be checked while parsing,
+// so the variant is chosen by hand
+impl<'de> Deserialize<'de> for PanelColor {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct PanelColorVisitor;
+
+        impl<'de> de::Visitor<'de> for PanelColorVisitor {
+            type Value = PanelColor;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "an RGB or RGBA tuple, or a CSS color string")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                csscolorparser::parse(v)
+                    .map(|_| PanelColor::Css(v.to_string()))
+                    .map_err(|e| E::custom(format!("invalid color {v:?}: {e}")))
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut c = Vec::with_capacity(4);
+                while let Some(v) = seq.next_element::<f32>()? {
+                    if c.len() == 4 {
+                        return Err(de::Error::invalid_length(5, &self));
+                    }
+                    c.push(v);
+                }
+                match c[..] {
+                    [r, g, b] => Ok(PanelColor::Rgb([r, g, b])),
+                    [r, g, b, a] => Ok(PanelColor::Rgba([r, g, b, a])),
+                    _ => Err(de::Error::invalid_length(c.len(), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(PanelColorVisitor)
+    }
+}
+
+/// outline drawn inside the edge of the panel background
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PanelBorder {
+    /// width in logical pixels
+    pub width: u32,
+    pub color: PanelColor,
+}
+
+/// drop shadow drawn outside of the panel background
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PanelShadow {
+    /// distance in logical pixels over which the shadow fades out
+    pub blur_radius: u32,
+    /// offset of the shadow in logical pixels, positive values move it right and down
+    pub offset: (i32, i32),
+    pub color: PanelColor,
+}
+
+impl PanelShadow {
+    /// distance in logical pixels which the shadow may extend beyond the panel background
+    pub fn extent(&self) -> u32 {
+        self.blur_radius
+            + self
+                .offset
+                .0
+                .unsigned_abs()
+                .max(self.offset.1.unsigned_abs())
+    }
 }
 
-// TODO configurable interpolation type?
 /// configurable autohide behavior
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct AutoHide {
     /// time in milliseconds without pointer focus before hiding
@@ -232,6 +569,9 @@ pub struct AutoHide {
     /// size of the handle in pixels
     /// should be > 0
     pub handle_size: u32,
+    /// curve of the hide / show transition
+    #[serde(default)]
+    pub easing: Easing,
 }
 
 impl Default for AutoHide {
@@ -240,10 +580,191 @@ impl Default for AutoHide {
             wait_time: 1000,
             transition_time: 200,
             handle_size: 4,
+            easing: Easing::default(),
+        }
+    }
+}
+
+/// interpolation curve of an animation
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub enum Easing {
+    Linear,
+    EaseInCubic,
+    EaseOutCubic,
+    EaseInOutCubic,
+    #[default]
+    Smootherstep,
+    /// damped spring which settles by the end of the animation
+    /// a damping ratio below 1.0 overshoots the target, lower values oscillate more
+    Spring {
+        damping: f32,
+    },
+    /// CSS style cubic bezier curve with control points (x1, y1) and (x2, y2)
+    /// x values are clamped between 0.0 and 1.0
+    CubicBezier(f32, f32, f32, f32),
+}
+
+impl Easing {
+    /// map the progress of an animation, between 0.0 and 1.0, to the progress along the curve
+    pub fn ease(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match *self {
+            Easing::Linear => t,
+            Easing::EaseInCubic => t * t * t,
+            Easing::EaseOutCubic => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOutCubic => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (2.0 - 2.0 * t).powi(3) / 2.0
+                }
+            }
+            Easing::Smootherstep => t * t * t * (t * (t * 6.0 - 15.0) + 10.0),
+            Easing::Spring { damping } => spring(damping, t),
+            Easing::CubicBezier(x1, y1, x2, y2) => cubic_bezier(x1, y1, x2, y2, t),
         }
     }
 }
 
+fn spring(damping: f32, t: f32) -> f32 {
+    let zeta = damping.clamp(0.05, 1.0);
+    // the envelope decays to 0.1% of the distance to the target by the end of the animation
+    let omega = 1000.0_f32.ln() / zeta;
+    // distance to the target
+    let distance = |t: f32| {
+        if zeta >= 1.0 {
+            (-omega * t).exp() * (1.0 + omega * t)
+        } else {
+            let omega_d = omega * (1.0 - zeta * zeta).sqrt();
+            (-zeta * omega * t).exp()
+                * ((omega_d * t).cos() + zeta * omega / omega_d * (omega_d * t).sin())
+        }
+    };
+    // the distance left at the end is removed gradually, so the spring ends on the target
+    1.0 - (distance(t) - t * distance(1.0))
+}
+
+fn cubic_bezier(x1: f32, y1: f32, x2: f32, y2: f32, t: f32) -> f32 {
+    // the curve passes through its end points, which bisection only approximates
+    if t == 0.0 || t == 1.0 {
+        return t;
+    }
+    let (x1, x2) = (x1.clamp(0.0, 1.0), x2.clamp(0.0, 1.0));
+    let bezier = |p1: f32, p2: f32, s: f32| {
+        3.0 * (1.0 - s) * (1.0 - s) * s * p1 + 3.0 * (1.0 - s) * s * s * p2 + s * s * s
+    };
+    // x is monotonic in s, so the parameter for t can be found by bisection
+    let (mut lo, mut hi) = (0.0, 1.0);
+    for _ in 0..24 {
+        let mid = (lo + hi) / 2.0;
+        if bezier(x1, x2, mid) < t {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    bezier(y1, y2, (lo + hi) / 2.0)
+}
+
+/// configurable animations of the panel, used when its config, the theme, or the maximized state changes
+/// durations are in milliseconds, and a duration of 0 applies the change immediately
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct AnimationConfig {
+    /// curve of the transitions
+    pub easing: Easing,
+    /// duration of background color transitions when the theme changes
+    pub theme_duration: u32,
+    /// duration of the restyling when a window on the output is maximized or unmaximized
+    pub maximize_duration: u32,
+    /// duration of the transition when the config of the panel changes
+    pub config_duration: u32,
+}
+
+impl Default for AnimationConfig {
+    fn default() -> Self {
+        Self {
+            easing: Easing::default(),
+            theme_duration: 300,
+            maximize_duration: 300,
+            config_duration: 300,
+        }
+    }
+}
+
+impl AnimationConfig {
+    pub fn theme_transition(&self) -> Duration {
+        Duration::from_millis(self.theme_duration.into())
+    }
+
+    pub fn maximize_transition(&self) -> Duration {
+        Duration::from_millis(self.maximize_duration.into())
+    }
+
+    pub fn config_transition(&self) -> Duration {
+        Duration::from_millis(self.config_duration.into())
+    }
+}
+
+/// kind of panel, which determines how it is sized and laid out
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub enum PanelKind {
+    /// stretches along the whole edge of the output
+    Panel,
+    /// sized to fit its applets and centered along the edge of the output
+    Dock(DockConfig),
+    /// sized to fit its applets, and always separated from the edge of the output by the margin
+    Floating(DockConfig),
+}
+
+impl Default for PanelKind {
+    fn default() -> Self {
+        PanelKind::Panel
+    }
+}
+
+/// configuration for panels of kind [`PanelKind::Dock`] and [`PanelKind::Floating`]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct DockConfig {
+    /// scale of the hovered applet, 1.0 disables magnification
+    /// limited to what fits in the padding and spacing, see [`CosmicPanelConfig::get_magnification`]
+    pub magnification: f32,
+    /// whether the dock stretches to the edges of the output while a window is maximized
+    pub stretch_on_maximize: bool,
+}
+
+impl Default for DockConfig {
+    fn default() -> Self {
+        Self {
+            magnification: 1.0,
+            stretch_on_maximize: true,
+        }
+    }
+}
+
+/// appearance of the panel while a window on its output is maximized, see [`CosmicPanelConfig::maximize`]
+/// unset fields fall back to the defaults described below
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct MaximizedStyle {
+    /// opacity of the background, 1.0 if unset
+    pub opacity: Option<f32>,
+    /// border radius, 0 if unset and the panel expands
+    pub border_radius: Option<u32>,
+    /// whether the panel keeps its gap to the anchored edge, no gap if unset and the panel expands
+    pub anchor_gap: Option<bool>,
+    /// margin on every side, 0 if unset and the panel expands
+    pub margin: Option<u16>,
+    /// whether the panel expands to the edges of the output like a [`PanelKind::Panel`]
+    /// if unset, panels expand unless they autohide or are docks with `stretch_on_maximize` disabled
+    pub expand: Option<bool>,
+    /// background, replacing custom colors with the theme background if unset
+    pub background: Option<CosmicPanelBackground>,
+}
+
 /// Configuration for the panel's ouput
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
@@ -254,6 +775,37 @@ pub enum CosmicPanelOuput {
     Active,
     /// show panel on a specific output
     Name(String),
+    /// show panel on every output matching the properties of a physical monitor
+    Match(OutputMatcher),
+    /// show panel on the outputs matching any of the listed outputs
+    Include(Vec<CosmicPanelOuput>),
+    /// show panel on all outputs except the ones matching any of the listed outputs
+    Exclude(Vec<CosmicPanelOuput>),
+    /// show panel on the first output advertised by the compositor
+    /// the compositor doesn't report which output is primary in the display settings, so this
+    /// is the output that was connected first, and it changes once that output is removed
+    Primary,
+}
+
+impl CosmicPanelOuput {
+    /// check if the panel should be shown on the output
+    /// always false for the active output, which is resolved by the compositor
+    pub fn matches(&self, output: &OutputIdentity) -> bool {
+        match self {
+            CosmicPanelOuput::All => true,
+            CosmicPanelOuput::Active => false,
+            CosmicPanelOuput::Name(n) => n == &output.name,
+            CosmicPanelOuput::Match(m) => m.matches(output),
+            CosmicPanelOuput::Include(list) => list.iter().any(|o| o.matches(output)),
+            CosmicPanelOuput::Exclude(list) => !list.iter().any(|o| o.matches(output)),
+            CosmicPanelOuput::Primary => output.is_first,
+        }
+    }
+
+    /// check if the panel has a separate space on each output it matches
+    pub fn is_per_output(&self) -> bool {
+        !matches!(self, CosmicPanelOuput::Active | CosmicPanelOuput::Name(_))
+    }
 }
 
 impl Display for CosmicPanelOuput {
@@ -262,10 +814,94 @@ impl Display for CosmicPanelOuput {
             CosmicPanelOuput::All => write!(f, "All"),
             CosmicPanelOuput::Active => write!(f, "Active"),
             CosmicPanelOuput::Name(n) => write!(f, "Name({})", n),
+            CosmicPanelOuput::Primary => write!(f, "Primary"),
+            CosmicPanelOuput::Match(_)
+            | CosmicPanelOuput::Include(_)
+            | CosmicPanelOuput::Exclude(_) => {
+                write!(f, "{}", ron::ser::to_string(self).map_err(|_| fmt::Error)?)
+            }
+        }
+    }
+}
+
+/// properties of an output, used to match it with [`OutputMatcher`]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct OutputIdentity {
+    /// connector name, e.g. `DP-1`
+    pub name: String,
+    pub make: String,
+    pub model: String,
+    /// human readable description, if it is reported by the compositor
+    pub description: Option<String>,
+    /// whether this is the first output advertised by the compositor of the connected ones
+    pub is_first: bool,
+}
+
+#[cfg(feature = "wayland-rs")]
+impl From<&OutputInfo> for OutputIdentity {
+    fn from(info: &OutputInfo) -> Self {
+        Self {
+            name: info.name.clone().unwrap_or_default(),
+            make: info.make.clone(),
+            model: info.model.clone(),
+            description: info.description.clone(),
+            is_first: false,
         }
     }
 }
 
+/// glob patterns for the properties of an output, which must all match
+/// `*` matches any sequence of characters, and `?` matches a single character
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields, default)]
+pub struct OutputMatcher {
+    /// connector name, e.g. `DP-*`
+    pub name: Option<String>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    /// never matches outputs without a description
+    pub description: Option<String>,
+}
+
+impl OutputMatcher {
+    pub fn matches(&self, output: &OutputIdentity) -> bool {
+        let matches = |pattern: &Option<String>, value: Option<&str>| match (pattern, value) {
+            (None, _) => true,
+            (Some(pattern), Some(value)) => glob_match(pattern, value),
+            (Some(_), None) => false,
+        };
+        matches(&self.name, Some(&output.name))
+            && matches(&self.make, Some(&output.make))
+            && matches(&self.model, Some(&output.model))
+            && matches(&self.description, output.description.as_deref())
+    }
+}
+
+fn glob_match(pattern: &str, s: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let s: Vec<char> = s.chars().collect();
+    let (mut p, mut i) = (0, 0);
+    // position of the last `*` in the pattern, and the position in `s` it was matched up to
+    let mut star = None;
+    while i < s.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == s[i]) {
+            p += 1;
+            i += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, i));
+            p += 1;
+        } else if let Some((star_p, star_i)) = star {
+            // let the last `*` match one more character
+            p = star_p + 1;
+            i = star_i + 1;
+            star = Some((star_p, star_i + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
 impl FromStr for CosmicPanelOuput {
     type Err = anyhow::Error;
 
@@ -273,9 +909,16 @@ impl FromStr for CosmicPanelOuput {
         match s {
             "All" => Ok(Self::All),
             "Active" => Ok(Self::Active),
+            "Primary" => Ok(Self::Primary),
             s if s.len() >= 6 && &s[..5] == "Name(" && s.ends_with(')') => {
                 Ok(Self::Name(s[5..s.len() - 1].to_string()))
             }
+            s if s.starts_with("Match(")
+                || s.starts_with("Include(")
+                || s.starts_with("Exclude(") =>
+            {
+                ron::from_str(s).map_err(|e| anyhow::anyhow!("Failed to parse output: {e}"))
+            }
             _ => bail!("Failed to parse output."),
         }
     }
@@ -288,15 +931,65 @@ impl Into<WrapperOutput> for CosmicPanelOuput {
             CosmicPanelOuput::All => WrapperOutput::All,
             CosmicPanelOuput::Active => WrapperOutput::Name(vec![]),
             CosmicPanelOuput::Name(n) => WrapperOutput::Name(vec![n]),
+            CosmicPanelOuput::Include(list) => {
+                list.into_iter()
+                    .map(Into::into)
+                    .fold(WrapperOutput::Name(vec![]), |acc, o| match (acc, o) {
+                        (WrapperOutput::Name(mut acc), WrapperOutput::Name(mut names)) => {
+                            acc.append(&mut names);
+                            WrapperOutput::Name(acc)
+                        }
+                        _ => WrapperOutput::All,
+                    })
+            }
+            // resolved by the panel when outputs are added
+            CosmicPanelOuput::Match(_)
+            | CosmicPanelOuput::Exclude(_)
+            | CosmicPanelOuput::Primary => WrapperOutput::All,
+        }
+    }
+}
+
+/// settings of a panel which are changed on the matching outputs
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct OutputOverride {
+    /// outputs which the override applies to
+    pub output: CosmicPanelOuput,
+    #[serde(default)]
+    pub size: Option<PanelSize>,
+    #[serde(default)]
+    pub zones: Option<Vec<AppletZone>>,
+    /// `Some(None)` disables autohide
+    #[serde(default)]
+    pub autohide: Option<Option<AutoHide>>,
+    #[serde(default)]
+    pub anchor: Option<PanelAnchor>,
+}
+
+#[cfg(feature = "wayland-rs")]
+impl OutputOverride {
+    /// replace the settings of the config which are set in the override
+    pub fn apply(&self, config: &mut CosmicPanelConfig) {
+        if let Some(size) = &self.size {
+            config.size = size.clone();
+        }
+        if let Some(zones) = &self.zones {
+            config.zones = zones.clone();
+        }
+        if let Some(autohide) = &self.autohide {
+            config.autohide = autohide.clone();
+        }
+        if let Some(anchor) = self.anchor {
+            config.anchor = anchor;
         }
     }
 }
 
 #[cfg(feature = "wayland-rs")]
-// TODO refactor to have separate dock mode config & panel mode config
 /// Config structure for the cosmic panel
 #[derive(Debug, Deserialize, Serialize, Clone, CosmicConfigEntry)]
-#[version = 1]
+#[version = 3]
 #[serde(deny_unknown_fields)]
 pub struct CosmicPanelConfig {
     /// profile name for this config, should be unique
@@ -315,48 +1008,49 @@ pub struct CosmicPanelConfig {
     pub output: CosmicPanelOuput,
     /// customized background, or
     pub background: CosmicPanelBackground,
-    /// list of plugins on the left / top and right / bottom of the panel
-    pub plugins_wings: Option<(Vec<String>, Vec<String>)>,
-    /// list of plugins in the center of the panel
-    pub plugins_center: Option<Vec<String>>,
-    /// whether the panel should stretch to the edges of output
-    pub expand_to_edges: bool,
+    /// zones of applets, zones with the same alignment are packed in order
+    pub zones: Vec<AppletZone>,
+    /// kind of the panel, with settings specific to it
+    pub kind: PanelKind,
     /// padding around the panel
     pub padding: u32,
     /// space between panel plugins
     pub spacing: u32,
     pub border_radius: u32,
-    // TODO autohide & exclusive zone should not be able to both be enabled at once
     /// exclusive zone
+    /// should not be enabled together with autohide, see [`CosmicPanelConfig::validate`]
     pub exclusive_zone: bool,
     /// enable autohide feature with the transitions lasting the supplied wait time and duration in millis
     pub autohide: Option<AutoHide>,
-    /// margin between the panel and the edge of the output
+    /// animations of the panel
+    pub animation: AnimationConfig,
+    /// margin between the panel and the edge of the output, see [`Self::get_margins`]
     pub margin: u16,
+    /// margins on each side of the panel, see [`Self::get_margins`]
+    pub margins: Option<PanelMargins>,
+    /// placement along the edge when the panel does not span all of it
+    pub alignment: PanelAlignment,
+    /// outline of the panel background
+    pub border: Option<PanelBorder>,
+    /// drop shadow of the panel background
+    pub shadow: Option<PanelShadow>,
+    /// minimum length of the panel bar
+    pub min_length: Option<PanelLength>,
+    /// maximum length of the panel bar, applets which do not fit are hidden
+    pub max_length: Option<PanelLength>,
     /// opacity of the panel
     pub opacity: f32,
+    /// appearance of the panel while a window on its output is maximized
+    pub maximized_style: MaximizedStyle,
+    /// changes applied on specific outputs, in order
+    pub overrides: Vec<OutputOverride>,
+    /// stacking priority, or None to derive it from the other settings, see [`CosmicPanelConfig::get_priority`]
+    pub priority: Option<u32>,
 }
 
 impl PartialEq for CosmicPanelConfig {
     fn eq(&self, other: &Self) -> bool {
-        self.name == other.name
-            && self.anchor == other.anchor
-            && self.anchor_gap == other.anchor_gap
-            && self.layer == other.layer
-            && self.keyboard_interactivity == other.keyboard_interactivity
-            && self.size == other.size
-            && self.output == other.output
-            && self.background == other.background
-            && self.plugins_wings == other.plugins_wings
-            && self.plugins_center == other.plugins_center
-            && self.expand_to_edges == other.expand_to_edges
-            && self.padding == other.padding
-            && self.spacing == other.spacing
-            && self.border_radius == other.border_radius
-            && self.exclusive_zone == other.exclusive_zone
-            && self.autohide == other.autohide
-            && self.margin == other.margin
-            && (self.opacity - other.opacity).abs() < 0.01
+        self.diff(other).is_empty()
     }
 }
 
@@ -372,16 +1066,25 @@ impl Default for CosmicPanelConfig {
             size: PanelSize::M,
             output: CosmicPanelOuput::All,
             background: CosmicPanelBackground::ThemeDefault,
-            plugins_wings: Default::default(),
-            plugins_center: Default::default(),
-            expand_to_edges: true,
+            zones: Default::default(),
+            kind: PanelKind::Panel,
             padding: 4,
             spacing: 4,
             exclusive_zone: true,
             autohide: None,
+            animation: AnimationConfig::default(),
             border_radius: 8,
             margin: 4,
+            margins: None,
+            alignment: PanelAlignment::Center,
+            border: None,
+            shadow: None,
+            min_length: None,
+            max_length: None,
             opacity: 0.8,
+            maximized_style: MaximizedStyle::default(),
+            overrides: Vec::new(),
+            priority: None,
         }
     }
 }
@@ -399,12 +1102,21 @@ impl CosmicPanelConfig {
 
     /// get the priority of the panel
     /// higher priority panels will be created first and given more space when competing for space
+    /// without a configured priority, it is derived from the kind and other settings of the panel,
+    /// resulting in values below 25000
     pub fn get_priority(&self) -> u32 {
-        let mut priority = if self.expand_to_edges() { 10000 } else { 0 };
+        if let Some(priority) = self.priority {
+            return priority;
+        }
+        let mut priority = match self.kind {
+            PanelKind::Panel => 20000,
+            PanelKind::Dock(_) => 10000,
+            PanelKind::Floating(_) => 0,
+        };
         if self.autohide().is_none() {
             priority += 1000;
         }
-        if self.margin == 0 {
+        if self.get_margins() == PanelMargins::default() {
             priority += 200;
         }
         if !self.anchor_gap {
@@ -416,20 +1128,48 @@ impl CosmicPanelConfig {
         priority
     }
 
-    /// get margin between the panel and the edge of the output
+    /// compare the stacking order of two panels, with the name breaking ties between equal priorities
+    pub fn cmp_priority(&self, other: &Self) -> Ordering {
+        self.get_priority()
+            .cmp(&other.get_priority())
+            .then_with(|| other.name.cmp(&self.name))
+    }
+
+    /// get margin between the panel and the edge of the output it is anchored to
     pub fn get_margin(&self) -> u16 {
-        self.margin
+        self.get_margins().edge(self.anchor)
     }
 
-    /// get the effective anchor gap margin
-    pub fn get_effective_anchor_gap(&self) -> u32 {
-        if self.anchor_gap {
-            self.margin as u32
+    /// get the margins on each side of the panel
+    /// `margins` takes precedence, `margin` is only used on every side if `margins` is unset
+    pub fn get_margins(&self) -> PanelMargins {
+        self.margins
+            .unwrap_or_else(|| PanelMargins::uniform(self.margin))
+    }
+
+    /// get the margins which apply while the panel is shown, these are only used with an anchor gap
+    pub fn get_effective_margins(&self) -> PanelMargins {
+        if self.has_anchor_gap() {
+            self.get_margins()
         } else {
-            0
+            PanelMargins::default()
         }
     }
 
+    /// get the space in logical pixels which is added to the thickness of the panel for its shadow
+    pub fn get_shadow_extent(&self) -> u32 {
+        self.shadow.as_ref().map_or(0, PanelShadow::extent)
+    }
+
+    /// get the effective anchor gap margin
+    pub fn get_effective_anchor_gap(&self) -> u32 {
+        self.get_effective_margins().edge(self.anchor) as u32
+    }
+
+    fn has_anchor_gap(&self) -> bool {
+        self.anchor_gap || matches!(self.kind, PanelKind::Floating(_))
+    }
+
     /// if autohide is configured, returns the duration of time which the panel should wait to hide when it has lost focus
     pub fn get_hide_wait(&self) -> Option<Duration> {
         self.autohide
@@ -446,6 +1186,13 @@ impl CosmicPanelConfig {
         )
     }
 
+    /// if autohide is configured, returns the curve of the hide / show transition
+    pub fn get_hide_easing(&self) -> Option<Easing> {
+        self.autohide
+            .as_ref()
+            .map(|AutoHide { easing, .. }| *easing)
+    }
+
     /// if autohide is configured, returns the size of the handle of the panel which should be exposed
     pub fn get_hide_handle(&self) -> Option<u32> {
         self.autohide
@@ -469,21 +1216,52 @@ impl CosmicPanelConfig {
         self.autohide.clone()
     }
 
-    /// get whether the panel should expand to cover the edges of the output
-    pub fn expand_to_edges(&self) -> bool {
-        self.expand_to_edges
+    pub fn kind(&self) -> &PanelKind {
+        &self.kind
     }
 
-    pub fn plugins_left(&self) -> Option<Vec<String>> {
-        self.plugins_wings.as_ref().map(|w| w.0.clone())
+    /// get whether the panel is sized to fit its applets
+    pub fn is_dock(&self) -> bool {
+        self.dock_config().is_some()
+    }
+
+    /// get the dock settings if the panel is a dock or floating
+    pub fn dock_config(&self) -> Option<&DockConfig> {
+        match &self.kind {
+            PanelKind::Panel => None,
+            PanelKind::Dock(dock) | PanelKind::Floating(dock) => Some(dock),
+        }
+    }
+
+    /// get the scale of the hovered applet, 1.0 if magnification is disabled
+    /// limited so the magnified applet stays within the surface and doesn't cover its neighbours,
+    /// see [`CosmicPanelConfig::max_magnification`]
+    pub fn get_magnification(&self) -> f32 {
+        self.dock_config()
+            .map_or(1.0, |dock| dock.magnification.min(self.max_magnification()))
+    }
+
+    /// get the largest magnification for which an applet grows into the padding of the panel and
+    /// the spacing to its neighbours at most
+    pub fn max_magnification(&self) -> f32 {
+        let applet_size = self
+            .get_applet_icon_size(false)
+            .saturating_add(2 * self.get_applet_padding(false) as u32);
+        1.0 + 2.0 * self.padding.min(self.spacing) as f32 / applet_size.max(1) as f32
+    }
+
+    /// get whether the panel should expand to cover the edges of the output
+    pub fn expand_to_edges(&self) -> bool {
+        !self.is_dock()
     }
 
-    pub fn plugins_center(&self) -> Option<Vec<String>> {
-        self.plugins_center.clone()
+    pub fn zones(&self) -> &[AppletZone] {
+        &self.zones
     }
 
-    pub fn plugins_right(&self) -> Option<Vec<String>> {
-        self.plugins_wings.as_ref().map(|w| w.1.clone())
+    /// get all applets of the panel, in the order of their zones
+    pub fn applets(&self) -> impl Iterator<Item = &AppletEntry> {
+        self.zones.iter().flat_map(|zone| zone.applets.iter())
     }
 
     pub fn anchor(&self) -> PanelAnchor {
@@ -509,7 +1287,7 @@ impl CosmicPanelConfig {
         }
     }
 
-    /// get constraints for the thickness of the panel bar
+    /// get constraints for the thickness and length of the panel bar
     pub fn get_dimensions(
         &self,
         output_dims: Option<(u32, u32)>,
@@ -517,37 +1295,77 @@ impl CosmicPanelConfig {
         gap: Option<u32>,
     ) -> (Option<Range<u32>>, Option<Range<u32>>) {
         let gap = gap.unwrap_or_else(|| self.get_effective_anchor_gap());
-        let bar_thickness = match &self.size {
-            PanelSize::XS => 8 + gap..61 + gap,
-            PanelSize::S => 8 + gap..81 + gap,
-            PanelSize::M => 8 + gap..101 + gap,
-            PanelSize::L => 8 + gap..121 + gap,
-            PanelSize::XL => 8 + gap..141 + gap,
-        };
-        assert!(2 * self.padding + gap < bar_thickness.end);
+        // the range is never empty, even for configs which fail validation
+        let bar_thickness =
+            MIN_THICKNESS + gap..self.size.get_max_thickness().max(MIN_THICKNESS) + 1 + gap;
         let o_h = suggested_length.unwrap_or_else(|| output_dims.unwrap_or_default().1);
         let o_w = suggested_length.unwrap_or_else(|| output_dims.unwrap_or_default().0);
 
         match self.anchor {
-            PanelAnchor::Left | PanelAnchor::Right => (Some(bar_thickness), Some(o_h..o_h + 1)),
-            PanelAnchor::Top | PanelAnchor::Bottom => (Some(o_w..o_w + 1), Some(bar_thickness)),
+            PanelAnchor::Left | PanelAnchor::Right => (
+                Some(bar_thickness),
+                Some(self.get_length_range(output_dims.map_or(o_h, |d| d.1), o_h)),
+            ),
+            PanelAnchor::Top | PanelAnchor::Bottom => (
+                Some(self.get_length_range(output_dims.map_or(o_w, |d| d.0), o_w)),
+                Some(bar_thickness),
+            ),
         }
     }
 
+    /// get constraints for the length of the panel bar, which never exceeds the available length
+    /// percentages are relative to the length of the output edge
+    pub fn get_length_range(&self, output_length: u32, available: u32) -> Range<u32> {
+        let max = self
+            .max_length
+            .map_or(available, |l| l.resolve(output_length).min(available));
+        let min = self
+            .min_length
+            .map_or(0, |l| l.resolve(output_length).min(max));
+        min..max + 1
+    }
+
     pub fn cosmic_config(name: &str) -> Result<Config, cosmic_config::Error> {
         let entry_name = format!("{NAME}.{}", name);
         Config::new(&entry_name, VERSION)
     }
 
+    /// get the config used on an output, with all matching overrides applied
+    pub fn for_output(&self, output: &OutputIdentity) -> Self {
+        let mut config = self.clone();
+        for o in self.overrides.iter().filter(|o| o.output.matches(output)) {
+            o.apply(&mut config);
+        }
+        config
+    }
+
+    /// apply the maximized style of the panel, see [`MaximizedStyle`]
     pub fn maximize(&mut self) {
-        self.opacity = 1.0;
-        if self.autohide().is_some() {
-            return;
+        let style = self.maximized_style.clone();
+        self.opacity = style.opacity.unwrap_or(1.0);
+        if let Some(background) = style.background {
+            self.background = background;
+        }
+        let expand = style.expand.unwrap_or_else(|| {
+            self.autohide().is_none()
+                && self
+                    .dock_config()
+                    .map_or(true, |dock| dock.stretch_on_maximize)
+        });
+        if expand {
+            self.kind = PanelKind::Panel;
+        }
+        // the shape only changes by default if the panel expands
+        if let Some(margin) = style.margin.or(expand.then_some(0)) {
+            self.margin = margin;
+            self.margins = None;
+        }
+        if let Some(border_radius) = style.border_radius.or(expand.then_some(0)) {
+            self.border_radius = border_radius;
+        }
+        if let Some(anchor_gap) = style.anchor_gap.or(expand.then_some(false)) {
+            self.anchor_gap = anchor_gap;
         }
-        self.expand_to_edges = true;
-        self.margin = 0;
-        self.border_radius = 0;
-        self.anchor_gap = false;
     }
 }
 
@@ -561,3 +1379,28 @@ impl WrapperConfig for CosmicPanelConfig {
         &self.name
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn easing_end_points() {
+        let curves = [
+            Easing::Linear,
+            Easing::EaseInCubic,
+            Easing::EaseOutCubic,
+            Easing::EaseInOutCubic,
+            Easing::Smootherstep,
+            Easing::Spring { damping: 0.05 },
+            Easing::Spring { damping: 0.3 },
+            Easing::Spring { damping: 1.0 },
+            Easing::CubicBezier(0.25, 0.1, 0.25, 1.0),
+            Easing::CubicBezier(0.7, -0.5, 0.3, 1.5),
+        ];
+        for easing in curves {
+            assert_eq!(easing.ease(0.0), 0.0, "{easing:?}");
+            assert_eq!(easing.ease(1.0), 1.0, "{easing:?}");
+        }
+    }
+}
@@ -0,0 +1,514 @@
+//! Validation of panel configurations
+
+use std::{collections::HashSet, fmt::Display};
+
+use crate::{
+    AppletConfig, AppletEntry, CosmicPanelBackground, CosmicPanelConfig,
+    CosmicPanelContainerConfig, PanelLength, MAX_GRADIENT_STOPS, MIN_THICKNESS,
+};
+
+/// severity of a validation issue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationSeverity {
+    /// the entry can still be used, but likely misbehaves
+    Warning,
+    /// the entry can't be used and should be rejected
+    Error,
+}
+
+/// kind of problem found in a panel configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationIssueKind {
+    /// padding on both sides doesn't fit in the maximum thickness of the panel
+    PaddingOverflow { padding: u32, max_thickness: u32 },
+    /// the maximum thickness of the panel is below [`MIN_THICKNESS`]
+    ThicknessTooSmall(u32),
+    /// applets with their padding don't fit in the maximum thickness of the panel
+    AppletOverflow {
+        applet_size: u32,
+        max_thickness: u32,
+    },
+    /// autohide and an exclusive zone are both enabled
+    AutohideWithExclusiveZone,
+    /// another entry in the container already uses this name
+    DuplicateName,
+    /// no applets are configured for the panel
+    EmptyAppletList,
+    /// the applet is listed more than once
+    DuplicateApplet(String),
+    /// another zone in the panel already uses this name
+    DuplicateZone(String),
+    /// the gradient has no stops, or more stops than can be rendered
+    GradientStops(usize),
+    /// the minimum length is larger than the maximum length, which takes precedence
+    LengthRange,
+    /// the margin was changed, but is ignored because the margins of each side are set
+    MarginOverridden(u16),
+    /// magnified applets don't fit in the padding and spacing around them, so the magnification
+    /// is reduced
+    MagnificationOverflow { applet_size: u32, room: u32 },
+}
+
+/// a problem found in a panel configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    /// name of the panel entry
+    pub entry: String,
+    /// index of the output override which caused the issue, if it is not in the entry itself
+    pub override_index: Option<usize>,
+    /// name of the field which caused the issue
+    pub field: &'static str,
+    pub severity: ValidationSeverity,
+    pub kind: ValidationIssueKind,
+}
+
+impl ValidationIssue {
+    fn new(
+        entry: &str,
+        field: &'static str,
+        severity: ValidationSeverity,
+        kind: ValidationIssueKind,
+    ) -> Self {
+        Self {
+            entry: entry.to_string(),
+            override_index: None,
+            field,
+            severity,
+            kind,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == ValidationSeverity::Error
+    }
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.override_index {
+            Some(i) => write!(f, "{}.overrides[{i}].{}: ", self.entry, self.field)?,
+            None => write!(f, "{}.{}: ", self.entry, self.field)?,
+        }
+        match &self.kind {
+            ValidationIssueKind::PaddingOverflow {
+                padding,
+                max_thickness,
+            } => write!(
+                f,
+                "padding of {padding} on both sides does not fit in the maximum thickness of {max_thickness}"
+            ),
+            ValidationIssueKind::ThicknessTooSmall(max_thickness) => write!(
+                f,
+                "maximum thickness of {max_thickness} is below the minimum of {MIN_THICKNESS}"
+            ),
+            ValidationIssueKind::AppletOverflow {
+                applet_size,
+                max_thickness,
+            } => write!(
+                f,
+                "applets of size {applet_size} do not fit in the maximum thickness of {max_thickness}"
+            ),
+            ValidationIssueKind::AutohideWithExclusiveZone => {
+                write!(f, "autohide and exclusive_zone should not both be enabled")
+            }
+            ValidationIssueKind::DuplicateName => write!(f, "name is used by another entry"),
+            ValidationIssueKind::EmptyAppletList => write!(f, "no applets are configured"),
+            ValidationIssueKind::DuplicateApplet(applet) => {
+                write!(f, "applet {applet} is listed more than once")
+            }
+            ValidationIssueKind::DuplicateZone(zone) => {
+                write!(f, "zone name {zone} is used more than once")
+            }
+            ValidationIssueKind::GradientStops(n) => write!(
+                f,
+                "gradient has {n} stops, but between 1 and {MAX_GRADIENT_STOPS} are supported"
+            ),
+            ValidationIssueKind::LengthRange => {
+                write!(f, "min_length is larger than max_length")
+            }
+            ValidationIssueKind::MarginOverridden(margin) => {
+                write!(f, "margin of {margin} is ignored, because margins is set")
+            }
+            ValidationIssueKind::MagnificationOverflow { applet_size, room } => write!(
+                f,
+                "magnified applets of size {applet_size} don't fit in a padding and spacing of {room}, the magnification is reduced"
+            ),
+        }
+    }
+}
+
+impl CosmicPanelConfig {
+    /// check the config for values which would crash or misbehave
+    /// each output override is checked applied to the entry, and only reports the issues which
+    /// the entry doesn't have by itself
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = self.validate_fields();
+        let entry_issues = issues.clone();
+        for (i, o) in self.overrides.iter().enumerate() {
+            let mut config = self.clone();
+            o.apply(&mut config);
+            issues.extend(
+                config
+                    .validate_fields()
+                    .into_iter()
+                    .filter(|issue| !entry_issues.contains(issue))
+                    .map(|issue| ValidationIssue {
+                        override_index: Some(i),
+                        ..issue
+                    }),
+            );
+        }
+        issues
+    }
+
+    fn validate_fields(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+
+        let max_thickness = self.size.get_max_thickness();
+        if max_thickness < MIN_THICKNESS {
+            issues.push(ValidationIssue::new(
+                &self.name,
+                "size",
+                ValidationSeverity::Error,
+                ValidationIssueKind::ThicknessTooSmall(max_thickness),
+            ));
+        }
+        if self.padding.saturating_mul(2) > max_thickness {
+            issues.push(ValidationIssue::new(
+                &self.name,
+                "padding",
+                ValidationSeverity::Error,
+                ValidationIssueKind::PaddingOverflow {
+                    padding: self.padding,
+                    max_thickness,
+                },
+            ));
+        }
+
+        let applet_size = self
+            .get_applet_icon_size(false)
+            .saturating_add(2 * self.get_applet_padding(false) as u32);
+        if applet_size > max_thickness {
+            issues.push(ValidationIssue::new(
+                &self.name,
+                "size",
+                ValidationSeverity::Error,
+                ValidationIssueKind::AppletOverflow {
+                    applet_size,
+                    max_thickness,
+                },
+            ));
+        }
+
+        if self.autohide.is_some() && self.exclusive_zone {
+            issues.push(ValidationIssue::new(
+                &self.name,
+                "exclusive_zone",
+                ValidationSeverity::Warning,
+                ValidationIssueKind::AutohideWithExclusiveZone,
+            ));
+        }
+
+        if self
+            .dock_config()
+            .is_some_and(|dock| dock.magnification > self.max_magnification())
+        {
+            issues.push(ValidationIssue::new(
+                &self.name,
+                "kind",
+                ValidationSeverity::Warning,
+                ValidationIssueKind::MagnificationOverflow {
+                    applet_size,
+                    room: self.padding.min(self.spacing),
+                },
+            ));
+        }
+
+        if let CosmicPanelBackground::LinearGradient { stops, .. } = &self.background {
+            if stops.is_empty() || stops.len() > MAX_GRADIENT_STOPS {
+                issues.push(ValidationIssue::new(
+                    &self.name,
+                    "background",
+                    ValidationSeverity::Warning,
+                    ValidationIssueKind::GradientStops(stops.len()),
+                ));
+            }
+        }
+
+        let min_exceeds_max = match (self.min_length, self.max_length) {
+            (Some(PanelLength::Pixels(min)), Some(PanelLength::Pixels(max))) => min > max,
+            (Some(PanelLength::Percent(min)), Some(PanelLength::Percent(max))) => min > max,
+            _ => false,
+        };
+        if min_exceeds_max {
+            issues.push(ValidationIssue::new(
+                &self.name,
+                "min_length",
+                ValidationSeverity::Warning,
+                ValidationIssueKind::LengthRange,
+            ));
+        }
+
+        if self.margins.is_some() && self.margin != CosmicPanelConfig::default().margin {
+            issues.push(ValidationIssue::new(
+                &self.name,
+                "margin",
+                ValidationSeverity::Warning,
+                ValidationIssueKind::MarginOverridden(self.margin),
+            ));
+        }
+
+        let mut zone_names = HashSet::new();
+        for zone in &self.zones {
+            if !zone_names.insert(zone.name.as_str()) {
+                issues.push(ValidationIssue::new(
+                    &self.name,
+                    "zones",
+                    ValidationSeverity::Warning,
+                    ValidationIssueKind::DuplicateZone(zone.name.clone()),
+                ));
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let mut is_empty = true;
+        for applet in self.applets() {
+            if !applet.enabled() {
+                continue;
+            }
+            is_empty = false;
+            if !seen.insert(applet.id()) {
+                issues.push(ValidationIssue::new(
+                    &self.name,
+                    "zones",
+                    ValidationSeverity::Warning,
+                    ValidationIssueKind::DuplicateApplet(applet.id().to_string()),
+                ));
+            }
+            if let AppletEntry::Configured(AppletConfig {
+                size: Some(size), ..
+            }) = applet
+            {
+                let applet_size = size
+                    .get_applet_icon_size(false)
+                    .saturating_add(2 * size.get_applet_padding(false) as u32);
+                if applet_size > max_thickness {
+                    issues.push(ValidationIssue::new(
+                        &self.name,
+                        "zones",
+                        ValidationSeverity::Warning,
+                        ValidationIssueKind::AppletOverflow {
+                            applet_size,
+                            max_thickness,
+                        },
+                    ));
+                }
+            }
+        }
+        if is_empty {
+            issues.push(ValidationIssue::new(
+                &self.name,
+                "zones",
+                ValidationSeverity::Warning,
+                ValidationIssueKind::EmptyAppletList,
+            ));
+        }
+
+        issues
+    }
+}
+
+impl CosmicPanelContainerConfig {
+    /// check every entry, and that entry names are unique
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        let mut names = HashSet::new();
+        for c in &self.config_list {
+            if !names.insert(c.name.as_str()) {
+                issues.push(ValidationIssue::new(
+                    &c.name,
+                    "name",
+                    ValidationSeverity::Error,
+                    ValidationIssueKind::DuplicateName,
+                ));
+            }
+            issues.append(&mut c.validate());
+        }
+        issues
+    }
+
+    /// remove entries with validation errors, then keep the first remaining entry for each name
+    /// returns all issues that were found
+    pub fn retain_valid(&mut self) -> Vec<ValidationIssue> {
+        let issues = self.validate();
+        self.config_list
+            .retain(|c| !c.validate().iter().any(|issue| issue.is_error()));
+        let mut names = HashSet::new();
+        self.config_list.retain(|c| names.insert(c.name.clone()));
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AppletZone, AutoHide, CosmicPanelOuput, OutputOverride, PanelMargins, PanelSize,
+        ZoneAlignment,
+    };
+
+    fn entry(name: &str) -> CosmicPanelConfig {
+        CosmicPanelConfig {
+            name: name.to_string(),
+            zones: vec![AppletZone::new(
+                "center",
+                ZoneAlignment::Center,
+                vec![AppletEntry::Id("com.system76.CosmicAppletTime".to_string())],
+            )],
+            ..Default::default()
+        }
+    }
+
+    fn kinds(config: &CosmicPanelConfig) -> Vec<ValidationIssueKind> {
+        config
+            .validate()
+            .into_iter()
+            .map(|issue| issue.kind)
+            .collect()
+    }
+
+    #[test]
+    fn valid() {
+        assert_eq!(entry("panel").validate(), []);
+    }
+
+    #[test]
+    fn issue_kinds() {
+        let mut config = entry("panel");
+        config.padding = 2;
+        config.size = PanelSize::Custom {
+            icon: 4,
+            padding: 0,
+            max_thickness: 6,
+        };
+        assert_eq!(kinds(&config), [ValidationIssueKind::ThicknessTooSmall(6)]);
+
+        let mut config = entry("panel");
+        config.padding = 60;
+        let issues = config.validate();
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].is_error());
+        assert!(matches!(
+            issues[0].kind,
+            ValidationIssueKind::PaddingOverflow { padding: 60, .. }
+        ));
+
+        let mut config = entry("panel");
+        config.autohide = Some(AutoHide::default());
+        assert_eq!(
+            kinds(&config),
+            [ValidationIssueKind::AutohideWithExclusiveZone]
+        );
+
+        let mut config = entry("panel");
+        config.margins = Some(PanelMargins::uniform(8));
+        assert_eq!(kinds(&config), []);
+        config.margin = 0;
+        assert_eq!(kinds(&config), [ValidationIssueKind::MarginOverridden(0)]);
+
+        let mut config = entry("panel");
+        let applet = config.zones[0].applets[0].clone();
+        config.zones[0].applets.push(applet);
+        config.zones.push(config.zones[0].clone());
+        config.min_length = Some(PanelLength::Pixels(200));
+        config.max_length = Some(PanelLength::Pixels(100));
+        assert_eq!(
+            kinds(&config),
+            [
+                ValidationIssueKind::LengthRange,
+                ValidationIssueKind::DuplicateZone("center".to_string()),
+                ValidationIssueKind::DuplicateApplet("com.system76.CosmicAppletTime".to_string()),
+                ValidationIssueKind::DuplicateApplet("com.system76.CosmicAppletTime".to_string()),
+                ValidationIssueKind::DuplicateApplet("com.system76.CosmicAppletTime".to_string()),
+            ]
+        );
+
+        let mut config = entry("panel");
+        config.zones.clear();
+        config.background = CosmicPanelBackground::LinearGradient {
+            angle: 0.0,
+            stops: Vec::new(),
+        };
+        let issues = config.validate();
+        assert_eq!(
+            kinds(&config),
+            [
+                ValidationIssueKind::GradientStops(0),
+                ValidationIssueKind::EmptyAppletList
+            ]
+        );
+        assert!(!issues.iter().any(ValidationIssue::is_error));
+    }
+
+    #[test]
+    fn overrides() {
+        let mut config = entry("panel");
+        config.padding = 2;
+        config.autohide = Some(AutoHide::default());
+        config.overrides.push(OutputOverride {
+            output: CosmicPanelOuput::Name("HDMI-A-1".to_string()),
+            size: Some(PanelSize::Custom {
+                icon: 4,
+                padding: 0,
+                max_thickness: 6,
+            }),
+            zones: None,
+            autohide: None,
+            anchor: None,
+        });
+        config.overrides.push(OutputOverride {
+            output: CosmicPanelOuput::Name("DP-1".to_string()),
+            size: None,
+            zones: None,
+            autohide: Some(None),
+            anchor: None,
+        });
+
+        let issues = config.validate();
+        // the issue of the entry is not reported again for the override which keeps it
+        assert_eq!(
+            issues
+                .iter()
+                .map(|issue| (issue.override_index, issue.kind.clone()))
+                .collect::<Vec<_>>(),
+            [
+                (None, ValidationIssueKind::AutohideWithExclusiveZone),
+                (Some(0), ValidationIssueKind::ThicknessTooSmall(6)),
+            ]
+        );
+        assert_eq!(
+            issues[1].to_string(),
+            "panel.overrides[0].size: maximum thickness of 6 is below the minimum of 8"
+        );
+    }
+
+    #[test]
+    fn retain_valid() {
+        let mut invalid = entry("panel");
+        invalid.padding = 60;
+        let mut container = CosmicPanelContainerConfig {
+            config_list: vec![invalid, entry("panel"), entry("panel"), entry("dock")],
+        };
+        let issues = container.retain_valid();
+        assert!(issues.iter().any(ValidationIssue::is_error));
+        // the first valid entry with a name is kept
+        assert_eq!(
+            container
+                .config_list
+                .iter()
+                .map(|c| (c.name.as_str(), c.padding))
+                .collect::<Vec<_>>(),
+            [("panel", 4), ("dock", 4)]
+        );
+    }
+}